@@ -0,0 +1,1609 @@
+//! Derivation of the warp route IDs and token IDs that the Sovereign SDK generates for Hyperlane
+//! warp routes.
+
+use bech32::{Bech32m, Hrp, primitives::decode::CheckedHrpstring};
+use sha2::{
+    Digest, Sha256,
+    digest::{OutputSizeUser, consts::U32},
+};
+use std::{fmt::Display, str::FromStr};
+
+use borsh::{BorshDeserialize, BorshSerialize};
+
+/// Bytes that are displayed and parsed as `0x`-prefixed hex.
+///
+/// `Ord` compares the raw bytes lexicographically, like `[u8]`, so iteration over a `BTreeMap` or
+/// `BTreeSet` of `HexString`s is deterministic. For `Vec<u8>` a value sorts right after its
+/// prefixes, e.g. `0x01 < 0x0100 < 0x02`, rather than by length or numeric value.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct HexString<T = Vec<u8>>(pub T)
+where
+    T: AsRef<[u8]>;
+
+/// A 20-byte EVM address.
+pub type Address = HexString<[u8; 20]>;
+/// A 32-byte hash, such as a warp route ID or token ID.
+pub type HexHash = HexString<[u8; 32]>;
+
+/// The token address used for a chain's native asset, which has no token contract: the zero
+/// address. Its warp route ID hashes 32 zero bytes, the separator, then the deployer.
+pub const NATIVE_TOKEN_ADDRESS: Address = HexString([0; 20]);
+
+#[cfg(feature = "rand")]
+impl<const N: usize> HexString<[u8; N]> {
+    /// Returns a value filled with random bytes, for placeholder inputs in tests and examples.
+    ///
+    /// ```
+    /// use sov_warp_utils::{Address, HexHash};
+    ///
+    /// let address = Address::random();
+    /// println!("{address}");
+    /// assert_ne!(HexHash::random(), HexHash::random());
+    /// ```
+    pub fn random() -> Self {
+        let mut bytes = [0u8; N];
+        rand::fill(&mut bytes);
+        Self(bytes)
+    }
+}
+
+/// Serializes as a `0x`-prefixed hex string in human-readable formats (e.g. JSON) and as a
+/// sequence of bytes otherwise (e.g. bincode).
+impl<T> serde::Serialize for HexString<T>
+where
+    T: AsRef<[u8]>,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        use serde::ser::SerializeSeq;
+        if serializer.is_human_readable() {
+            serializer.serialize_str(&self.to_string())
+        } else {
+            let inner_ref = self.0.as_ref();
+            let mut seq = serializer.serialize_seq(Some(inner_ref.len()))?;
+            for element in inner_ref {
+                seq.serialize_element(element)?;
+            }
+            seq.end()
+        }
+    }
+}
+
+/// The most bytes the non-human-readable [`serde::Deserialize`] impl of [`HexString`] accepts, so
+/// a length declared by untrusted binary input can't trigger an unbounded allocation. Use
+/// [`hex_string_serde::deserialize_bounded`] for a different limit.
+pub const MAX_DESERIALIZED_LEN: usize = 1 << 20;
+
+/// Deserializes a sequence of at most `MAX` bytes.
+struct BoundedBytesVisitor<const MAX: usize>;
+
+impl<'de, const MAX: usize> serde::de::Visitor<'de> for BoundedBytesVisitor<MAX> {
+    type Value = Vec<u8>;
+
+    fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "a sequence of at most {MAX} bytes")
+    }
+
+    fn visit_seq<A: serde::de::SeqAccess<'de>>(self, mut seq: A) -> Result<Vec<u8>, A::Error> {
+        let declared = seq.size_hint().unwrap_or(0);
+        if declared > MAX {
+            return Err(serde::de::Error::invalid_length(declared, &self));
+        }
+        let mut bytes = Vec::with_capacity(declared);
+        while let Some(byte) = seq.next_element()? {
+            if bytes.len() == MAX {
+                return Err(serde::de::Error::invalid_length(bytes.len() + 1, &self));
+            }
+            bytes.push(byte);
+        }
+        Ok(bytes)
+    }
+}
+
+/// Deserializes a [`HexString`] like its [`serde::Deserialize`] impl, accepting byte sequences of
+/// at most `MAX` bytes.
+fn deserialize_bounded<'de, const MAX: usize, D, T>(
+    deserializer: D,
+) -> Result<HexString<T>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: TryFrom<Vec<u8>> + AsRef<[u8]>,
+{
+    let bytes = if deserializer.is_human_readable() {
+        let string: String = serde::Deserialize::deserialize(deserializer)?;
+        parse_vec_u8(&string).map_err(serde::de::Error::custom)?
+    } else {
+        deserializer.deserialize_seq(BoundedBytesVisitor::<MAX>)?
+    };
+
+    Ok(HexString(bytes.try_into().map_err(|_| {
+        serde::de::Error::custom("Invalid hex string length")
+    })?))
+}
+
+/// The inverse of the [`serde::Serialize`] impl: a hex string in human-readable formats and a
+/// sequence of bytes otherwise. Hex strings are parsed like [`FromStr`], so `"0xabcd"` and `"abcd"`
+/// deserialize to the same value. Fails if the decoded length doesn't fit `T`, or if a byte
+/// sequence is longer than [`MAX_DESERIALIZED_LEN`].
+impl<'de, T> serde::Deserialize<'de> for HexString<T>
+where
+    T: TryFrom<Vec<u8>> + AsRef<[u8]>,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        deserialize_bounded::<MAX_DESERIALIZED_LEN, D, T>(deserializer)
+    }
+}
+
+impl<T: BorshSerialize + AsRef<[u8]>> BorshSerialize for HexString<T> {
+    fn serialize<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        self.0.serialize(writer)
+    }
+}
+
+impl<T: BorshDeserialize + AsRef<[u8]>> BorshDeserialize for HexString<T> {
+    fn deserialize_reader<R: std::io::Read>(reader: &mut R) -> std::io::Result<Self> {
+        T::deserialize_reader(reader).map(Self)
+    }
+}
+
+/// Parses hex with an optional `0x` prefix. Malformed input of any kind (empty, odd-length,
+/// non-hex or non-ASCII, or the wrong length for `T`) returns an error; parsing never panics.
+impl<T: TryFrom<Vec<u8>> + AsRef<[u8]>> FromStr for HexString<T> {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bytes = parse_vec_u8(s)?;
+        Ok(HexString(bytes.try_into().map_err(|_| {
+            anyhow::anyhow!("Invalid hex string length")
+        })?))
+    }
+}
+
+impl<T> Display for HexString<T>
+where
+    T: AsRef<[u8]>,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "0x{}", hex::encode(&self.0))
+    }
+}
+
+impl<T: AsRef<[u8]>> AsRef<[u8]> for HexString<T> {
+    fn as_ref(&self) -> &[u8] {
+        self.0.as_ref()
+    }
+}
+
+/// Allows looking up `HexString` keys in maps and sets by `&[u8]`. This is consistent with the
+/// derived `Hash`, `Eq` and `Ord`, which compare the inner bytes exactly like `[u8]` does; `Vec<u8>`
+/// and arrays both hash and compare as their slices.
+impl<T: AsRef<[u8]>> std::borrow::Borrow<[u8]> for HexString<T> {
+    fn borrow(&self) -> &[u8] {
+        self.0.as_ref()
+    }
+}
+
+/// Identical to [`Display`], so `{:?}` and `{}` always agree.
+impl<T> std::fmt::Debug for HexString<T>
+where
+    T: AsRef<[u8]>,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(self, f)
+    }
+}
+
+impl HexString {
+    /// Parses `s` as hex, failing unless it decodes to exactly `N` bytes. Equivalent to parsing a
+    /// `HexString<[u8; N]>`, without relying on type inference to pick the length.
+    ///
+    /// ```
+    /// use sov_warp_utils::HexString;
+    ///
+    /// let hash = HexString::parse_exact::<32>(
+    ///     "0x9c081539d40ef7b02d359c5d694e006f0c1130097466cd22d062e07065c6987a",
+    /// )
+    /// .unwrap();
+    /// assert_eq!(hash.0[0], 0x9c);
+    /// assert!(HexString::parse_exact::<32>("0xabcd").is_err());
+    /// ```
+    pub fn parse_exact<const N: usize>(s: &str) -> anyhow::Result<HexString<[u8; N]>> {
+        HexString(parse_vec_u8(s)?).try_into_fixed()
+    }
+
+    /// Converts to a fixed-size `HexString<[u8; N]>`, such as an [`Address`] or [`HexHash`],
+    /// failing unless this holds exactly `N` bytes.
+    pub fn try_into_fixed<const N: usize>(self) -> anyhow::Result<HexString<[u8; N]>> {
+        let len = self.0.len();
+        self.0
+            .try_into()
+            .map(HexString)
+            .map_err(|_| anyhow::anyhow!("Expected {N} bytes of hex, got {len}"))
+    }
+}
+
+impl<const N: usize> HexString<[u8; N]> {
+    /// Adds one, treating the bytes as a big-endian integer, e.g. `0x..00ff` becomes `0x..0100`.
+    /// Returns `None` if every byte is `0xff`.
+    pub fn increment(&self) -> Option<Self> {
+        let mut bytes = self.0;
+        for byte in bytes.iter_mut().rev() {
+            let (sum, carry) = byte.overflowing_add(1);
+            *byte = sum;
+            if !carry {
+                return Some(HexString(bytes));
+            }
+        }
+        None
+    }
+}
+
+impl<T: AsRef<[u8]>> HexString<T> {
+    /// Formats like [`Display`], but without leading zero bytes. At least one byte is kept, so an
+    /// all-zero value formats as `0x00`. This only affects display, never the value itself.
+    pub fn to_trimmed_string(&self) -> String {
+        let bytes = self.0.as_ref();
+        let start = bytes
+            .iter()
+            .position(|&b| b != 0)
+            .unwrap_or(bytes.len().saturating_sub(1));
+        format!("0x{}", hex::encode(&bytes[start..]))
+    }
+
+    /// Transforms the backing bytes with `f`, e.g. to reverse a [`HexHash`]'s byte order or to
+    /// move them into another container.
+    ///
+    /// ```
+    /// use sov_warp_utils::{HexHash, HexString};
+    ///
+    /// let hash = HexString::parse_exact::<32>(
+    ///     "0x9c081539d40ef7b02d359c5d694e006f0c1130097466cd22d062e07065c6987a",
+    /// )
+    /// .unwrap();
+    /// let reversed: HexHash = hash.map_inner(|mut bytes| {
+    ///     bytes.reverse();
+    ///     bytes
+    /// });
+    /// assert_eq!(
+    ///     reversed.to_string(),
+    ///     "0x7a98c66570e062d022cd66740930110c6f004e695d9c352db0f70ed43915089c",
+    /// );
+    /// let prefix = hash.map_inner(|bytes| bytes[..4].to_vec());
+    /// assert_eq!(prefix.to_string(), "0x9c081539");
+    /// ```
+    pub fn map_inner<U: AsRef<[u8]>>(self, f: impl FnOnce(T) -> U) -> HexString<U> {
+        HexString(f(self.0))
+    }
+}
+
+/// [`serde`] (de)serialization functions for [`HexString`], to be used with
+/// `#[serde(with = "...")]`.
+pub mod hex_string_serde {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use super::HexString;
+
+    /// Serializes `data` as hex string using lowercase characters and prefixing with '0x'.
+    ///
+    /// Lowercase characters are used (e.g. `f9b4ca`). The resulting string's length
+    /// is always even, each byte in data is always encoded using two hex digits.
+    /// Thus, the resulting string contains exactly twice as many bytes as the input
+    /// data.
+    pub fn serialize<S, T>(data: T, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+        T: AsRef<[u8]>,
+    {
+        HexString::<T>(data).serialize(serializer)
+    }
+
+    /// Deserializes a hex string into raw bytes.
+    ///
+    /// Both upper and lower case characters are valid in the input string and can
+    /// even be mixed.
+    pub fn deserialize<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+    where
+        D: Deserializer<'de>,
+        T: TryFrom<Vec<u8>> + AsRef<[u8]>,
+    {
+        HexString::<T>::deserialize(deserializer).map(|s| s.0)
+    }
+
+    /// Like [`deserialize`], but accepts byte sequences of at most `MAX` bytes in
+    /// non-human-readable formats instead of [`MAX_DESERIALIZED_LEN`](super::MAX_DESERIALIZED_LEN),
+    /// e.g. `#[serde(deserialize_with = "hex_string_serde::deserialize_bounded::<4096, _, _>")]`.
+    pub fn deserialize_bounded<'de, const MAX: usize, D, T>(deserializer: D) -> Result<T, D::Error>
+    where
+        D: Deserializer<'de>,
+        T: TryFrom<Vec<u8>> + AsRef<[u8]>,
+    {
+        super::deserialize_bounded::<MAX, D, T>(deserializer).map(|s| s.0)
+    }
+}
+
+fn parse_vec_u8(input: &str) -> anyhow::Result<Vec<u8>> {
+    let s = input.strip_prefix("0x").unwrap_or(input);
+    if !s.len().is_multiple_of(2) {
+        anyhow::bail!("hex string has odd number of digits ({})", s.len());
+    }
+
+    hex::decode(s).map_err(|e| match e {
+        hex::FromHexError::InvalidHexCharacter { c, index } => anyhow::anyhow!(
+            "Failed to decode hex string {}, error: invalid character {:?} at offset {}",
+            abbreviate(input),
+            c,
+            index + (input.len() - s.len()),
+        ),
+        e => anyhow::anyhow!(
+            "Failed to decode hex string {}, error: {}",
+            abbreviate(input),
+            e
+        ),
+    })
+}
+
+/// Shortens long strings to their first and last 8 characters, so errors about untrusted input
+/// stay readable and don't flood logs.
+fn abbreviate(s: &str) -> String {
+    let chars: Vec<char> = s.chars().collect();
+    if chars.len() <= 20 {
+        return s.to_string();
+    }
+    let head: String = chars[..8].iter().collect();
+    let tail: String = chars[chars.len() - 8..].iter().collect();
+    format!("{head}...{tail}")
+}
+
+/// Parses an address written as a decimal integer, optionally prefixed with `0d`, into its
+/// 20-byte big-endian form.
+pub fn address_from_decimal(s: &str) -> anyhow::Result<Address> {
+    let digits = s.strip_prefix("0d").unwrap_or(s);
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        anyhow::bail!("Failed to parse decimal address {s}: expected only the digits 0-9");
+    }
+    let mut bytes = [0u8; 20];
+    for digit in digits.bytes() {
+        let mut carry = u16::from(digit - b'0');
+        for byte in bytes.iter_mut().rev() {
+            let value = u16::from(*byte) * 10 + carry;
+            *byte = value as u8;
+            carry = value >> 8;
+        }
+        if carry != 0 {
+            anyhow::bail!("Decimal address {s} doesn't fit in 20 bytes");
+        }
+    }
+    Ok(HexString(bytes))
+}
+
+/// Left-pads a 20-byte EVM address with zeros to the 32-byte form Hyperlane uses for remote token
+/// IDs. This is the only implementation of the padding: [`HexHash::from`], [`Address::to_h256`]
+/// and every preimage use it.
+pub fn pad_address_to_32(address: Address) -> [u8; 32] {
+    let mut padded = [0u8; 32];
+    padded[12..].copy_from_slice(&address.0);
+    padded
+}
+
+/// Left-pads the address to 32 bytes, as with [`pad_address_to_32`].
+impl From<Address> for HexHash {
+    fn from(address: Address) -> Self {
+        HexString(pad_address_to_32(address))
+    }
+}
+
+impl Address {
+    /// The Hyperlane `bytes32` (`H256`) form of this address: left-padded with 12 zero bytes.
+    pub fn to_h256(&self) -> [u8; 32] {
+        pad_address_to_32(*self)
+    }
+
+    /// The inverse of [`Address::to_h256`]. Fails unless the first 12 bytes are zero, since the
+    /// `bytes32` then doesn't hold an EVM address.
+    pub fn from_h256(bytes: [u8; 32]) -> anyhow::Result<Self> {
+        if bytes[..12].iter().any(|&b| b != 0) {
+            return Err(anyhow::anyhow!(
+                "Failed to convert {} to an address, error: the first 12 bytes are not zero",
+                HexString(bytes)
+            ));
+        }
+        let mut address = [0u8; 20];
+        address.copy_from_slice(&bytes[12..]);
+        Ok(HexString(address))
+    }
+}
+
+impl HexHash {
+    /// The Hyperlane `bytes32` (`H256`) form of this hash, which is its bytes unchanged.
+    pub fn to_h256(&self) -> [u8; 32] {
+        self.0
+    }
+
+    /// The inverse of [`HexHash::to_h256`].
+    pub fn from_h256(bytes: [u8; 32]) -> Self {
+        HexString(bytes)
+    }
+}
+
+/// The byte hashed between the padded token address and the deployer in a warp route ID
+/// preimage.
+pub const SEPARATOR_BYTE: u8 = 0x00;
+
+/// The start of the conventional token name, which is followed by the `0x`-prefixed warp route
+/// ID. See [`get_token_name`].
+pub const TOKEN_NAME_PREFIX: &str = "Synthetic token for ";
+
+/// `remote_token_id_bytes || SEPARATOR_BYTE || DEPLOYER_ADDRESS`
+///
+/// The token address is padded to 32 bytes but the deployer is hashed as its raw 20 bytes.
+pub fn get_warp_route_id(token_address: Address, deployer: Address) -> HexHash {
+    get_warp_route_id_with::<Sha256>(token_address, deployer)
+}
+
+/// A SHA-256 implementation to derive IDs with, such as a hardware-accelerated one. Blanket
+/// implemented for every [`Digest`] with a 32-byte output.
+pub trait Sha256Digest: Digest + OutputSizeUser<OutputSize = U32> {}
+
+impl<D: Digest + OutputSizeUser<OutputSize = U32>> Sha256Digest for D {}
+
+/// Like [`get_warp_route_id`], but hashes with `D` instead of [`Sha256`].
+pub fn get_warp_route_id_with<D: Sha256Digest>(
+    token_address: Address,
+    deployer: Address,
+) -> HexHash {
+    let mut hasher = D::new();
+    hasher.update(pad_address_to_32(token_address));
+    hasher.update([SEPARATOR_BYTE]);
+    hasher.update(deployer.0);
+    HexString(hasher.finalize().into())
+}
+
+/// WARP_ROUTE_ID || "Synthetic token for 0x{hex(WARP_ROUTE_ID)} || {LOCAL_DECIMALS as u8}
+pub fn get_token_id(warp_route_id: HexHash, decimals: u8) -> HexHash {
+    get_token_id_with_name(warp_route_id, &get_token_name(warp_route_id), decimals)
+}
+
+/// Like [`get_token_id`], but hashes `token_name` verbatim instead of the conventional
+/// `Synthetic token for ...` name. Any other name produces a different token ID.
+pub fn get_token_id_with_name(warp_route_id: HexHash, token_name: &str, decimals: u8) -> HexHash {
+    get_token_id_with::<Sha256>(warp_route_id, token_name, decimals)
+}
+
+/// Like [`get_token_id_with_name`], but hashes with `D` instead of [`Sha256`].
+pub fn get_token_id_with<D: Sha256Digest>(
+    warp_route_id: HexHash,
+    token_name: &str,
+    decimals: u8,
+) -> HexHash {
+    let hash = D::digest(preimage::token_id(warp_route_id, token_name, decimals));
+    token_id_from_hash(hash.into(), decimals)
+}
+
+/// The token ID for the SHA-256 `hash` of its preimage: the hash with its final byte replaced by
+/// `decimals`.
+fn token_id_from_hash(mut hash: [u8; 32], decimals: u8) -> HexHash {
+    hash[31] = decimals;
+    HexString(hash)
+}
+
+/// Computes both the warp route ID and the token ID with the conventional token name, reusing one
+/// hasher. The results are identical to [`get_warp_route_id`] and [`get_token_id`].
+pub fn derive(deployer: Address, token_address: Address, decimals: u8) -> (HexHash, HexHash) {
+    let mut hasher = Sha256::default();
+    hasher.update(pad_address_to_32(token_address));
+    hasher.update([SEPARATOR_BYTE]);
+    hasher.update(deployer.0);
+    let warp_route_id = HexString(hasher.finalize_reset().into());
+    let token_name = get_token_name(warp_route_id);
+    hasher.update(preimage::token_id(warp_route_id, &token_name, decimals));
+    let token_id = token_id_from_hash(hasher.finalize().into(), decimals);
+    (warp_route_id, token_id)
+}
+
+/// A second implementation of [`get_warp_route_id`] and [`get_token_id_with_name`], for
+/// cross-checking them: the warp route ID preimage is built in full instead of being fed to the
+/// hasher piece by piece, and each preimage is hashed in one call by a fresh hasher. The token ID
+/// preimage comes from the same builder as everywhere else, so this checks the hashing rather
+/// than the preimage layout.
+pub fn derive_reference(
+    deployer: Address,
+    token_address: Address,
+    decimals: u8,
+    token_name: &str,
+) -> (HexHash, HexHash) {
+    let warp_route_id =
+        HexString(Sha256::digest(preimage::warp_route_id(token_address, deployer)).into());
+    let hash = Sha256::digest(preimage::token_id(warp_route_id, token_name, decimals));
+    (warp_route_id, token_id_from_hash(hash.into(), decimals))
+}
+
+/// Like [`derive_reference`], but hashes each full preimage with `hash`, which may fail, e.g.
+/// because it delegates to an HSM. The token is named by `token_name`, given the warp route ID.
+/// Returns the warp route ID, the token name and the token ID.
+pub fn derive_with_hash_fn<E>(
+    deployer: Address,
+    token_address: Address,
+    decimals: u8,
+    token_name: impl FnOnce(HexHash) -> String,
+    mut hash: impl FnMut(&[u8]) -> Result<[u8; 32], E>,
+) -> Result<(HexHash, String, HexHash), E> {
+    let warp_route_id = HexString(hash(&preimage::warp_route_id(token_address, deployer))?);
+    let token_name = token_name(warp_route_id);
+    let token_id = hash(&preimage::token_id(warp_route_id, &token_name, decimals))?;
+    Ok((
+        warp_route_id,
+        token_name,
+        token_id_from_hash(token_id, decimals),
+    ))
+}
+
+/// Builders of the full preimages. [`preimage::token_id`] is the only place the token ID preimage
+/// is laid out; every derivation of a token ID hashes its output.
+mod preimage {
+    use super::{Address, HexHash, SEPARATOR_BYTE, pad_address_to_32};
+
+    /// `remote_token_id_bytes || SEPARATOR_BYTE || DEPLOYER_ADDRESS`, with the token address
+    /// left-padded to 32 bytes.
+    pub fn warp_route_id(token_address: Address, deployer: Address) -> Vec<u8> {
+        let mut preimage = pad_address_to_32(token_address).to_vec();
+        preimage.push(SEPARATOR_BYTE);
+        preimage.extend_from_slice(&deployer.0);
+        preimage
+    }
+
+    /// `WARP_ROUTE_ID || token_name || decimals`, before the last byte of the hash is replaced.
+    pub fn token_id(warp_route_id: HexHash, token_name: &str, decimals: u8) -> Vec<u8> {
+        let mut preimage = warp_route_id.0.to_vec();
+        preimage.extend_from_slice(token_name.as_bytes());
+        // The decimals are the only integer hashed, and a single byte has no byte order.
+        preimage.push(decimals);
+        preimage
+    }
+}
+
+/// Internal helpers, exposed for testing the derivation. Unstable: only available with the
+/// `test-internals` feature, and may change or be removed in any release.
+#[cfg(feature = "test-internals")]
+#[doc(hidden)]
+pub mod __private {
+    pub use crate::preimage::{
+        token_id as token_id_preimage, warp_route_id as warp_route_id_preimage,
+    };
+}
+
+/// The name of the synthetic token created for `warp_route_id`.
+pub fn get_token_name(warp_route_id: HexHash) -> String {
+    format!("{TOKEN_NAME_PREFIX}{warp_route_id}")
+}
+
+/// A way of deriving warp route IDs and token IDs from their inputs. [`SovV1Scheme`] is the
+/// derivation the Sovereign SDK uses; other implementors are for experimentation.
+pub trait DerivationScheme {
+    /// Derives the warp route ID of `token_address` deployed by `deployer`.
+    fn warp_route_id(&self, token_address: Address, deployer: Address) -> HexHash;
+
+    /// Derives the token ID of the synthetic token named `token_name` for `warp_route_id`.
+    fn token_id(&self, warp_route_id: HexHash, token_name: &str, decimals: u8) -> HexHash;
+
+    /// The conventional name of the synthetic token for `warp_route_id`.
+    fn token_name(&self, warp_route_id: HexHash) -> String {
+        get_token_name(warp_route_id)
+    }
+}
+
+/// The Sovereign SDK derivation: [`get_warp_route_id`] and [`get_token_id_with_name`].
+#[derive(Copy, Clone, Debug, Default)]
+pub struct SovV1Scheme;
+
+impl DerivationScheme for SovV1Scheme {
+    fn warp_route_id(&self, token_address: Address, deployer: Address) -> HexHash {
+        get_warp_route_id(token_address, deployer)
+    }
+
+    fn token_id(&self, warp_route_id: HexHash, token_name: &str, decimals: u8) -> HexHash {
+        get_token_id_with_name(warp_route_id, token_name, decimals)
+    }
+}
+
+/// An example alternative to [`SovV1Scheme`] that hashes the raw 20-byte token address instead of
+/// padding it to 32 bytes, and otherwise matches it. No chain uses this; it is useful for checking
+/// whether an unexpected warp route ID was derived from an unpadded address.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct UnpaddedScheme;
+
+impl DerivationScheme for UnpaddedScheme {
+    fn warp_route_id(&self, token_address: Address, deployer: Address) -> HexHash {
+        let mut hasher = Sha256::default();
+        hasher.update(token_address.0);
+        hasher.update([SEPARATOR_BYTE]);
+        hasher.update(deployer.0);
+        HexString(hasher.finalize().into())
+    }
+
+    fn token_id(&self, warp_route_id: HexHash, token_name: &str, decimals: u8) -> HexHash {
+        SovV1Scheme.token_id(warp_route_id, token_name, decimals)
+    }
+}
+
+/// The default `token_` prefix of bech32m token IDs.
+pub fn token_hrp() -> Hrp {
+    Hrp::parse("token_").expect("token_ is a valid prefix")
+}
+
+/// The `group_` prefix of bech32m group IDs.
+pub fn group_hrp() -> Hrp {
+    Hrp::parse("group_").expect("group_ is a valid prefix")
+}
+
+/// An identifier for a set of token IDs: the SHA-256 of the sorted, deduplicated IDs
+/// concatenated. The result doesn't depend on the order of `token_ids` or on repeated entries.
+pub fn get_group_id(token_ids: &[HexHash]) -> HexHash {
+    let mut token_ids = token_ids.to_vec();
+    token_ids.sort();
+    token_ids.dedup();
+    let mut hasher = Sha256::default();
+    for token_id in &token_ids {
+        hasher.update(token_id.0);
+    }
+    HexString(hasher.finalize().into())
+}
+
+/// Encodes a token ID as a bech32m string with the given prefix.
+pub fn format_token_id(id: HexHash, hrp: Hrp) -> String {
+    bech32::encode::<Bech32m>(hrp, &id.0).expect("Failed to format bech32")
+}
+
+/// Decodes a `token_...` bech32m string back into the raw token ID, rejecting payloads that
+/// aren't exactly 32 bytes. The `bech32` crate separates the prefix from the data at the last `1`,
+/// which is unambiguous since the data charset excludes `1`; never split these strings by hand.
+pub fn parse_token_id(s: &str) -> anyhow::Result<HexHash> {
+    let checked = CheckedHrpstring::new::<Bech32m>(s)
+        .map_err(|e| anyhow::anyhow!("Failed to decode bech32 string {}, error: {}", s, e))?;
+    if checked.hrp() != token_hrp() {
+        anyhow::bail!("Expected a token_ prefix, found {}", checked.hrp());
+    }
+    let bytes: Vec<u8> = checked.byte_iter().collect();
+    match bytes.len() {
+        len if len < 32 => {
+            anyhow::bail!("Token ID payload too short: expected 32 bytes, got {len}")
+        }
+        len if len > 32 => anyhow::bail!("Token ID payload too long: expected 32 bytes, got {len}"),
+        _ => Ok(HexString(
+            bytes.try_into().expect("length was checked above"),
+        )),
+    }
+}
+
+/// The inputs and derived IDs of a single warp route.
+#[derive(serde::Serialize)]
+pub struct WarpRouteResult {
+    pub deployer: Address,
+    pub token_address: Address,
+    pub decimals: u8,
+    pub warp_route_id: HexHash,
+    pub token_name: String,
+    /// The bech32m-encoded token ID
+    pub token_id: String,
+}
+
+impl WarpRouteResult {
+    /// Derives the warp route ID and token ID, formatting the token ID with `hrp`.
+    pub fn compute(deployer: Address, token_address: Address, decimals: u8, hrp: Hrp) -> Self {
+        Self::compute_with_token_name(deployer, token_address, decimals, None, hrp)
+    }
+
+    /// Like [`WarpRouteResult::compute`], but hashes `token_name` verbatim when given instead of
+    /// the conventional name. See [`get_token_id_with_name`].
+    pub fn compute_with_token_name(
+        deployer: Address,
+        token_address: Address,
+        decimals: u8,
+        token_name: Option<&str>,
+        hrp: Hrp,
+    ) -> Self {
+        Self::compute_with_scheme(
+            &SovV1Scheme,
+            deployer,
+            token_address,
+            decimals,
+            token_name,
+            hrp,
+        )
+    }
+
+    /// Like [`WarpRouteResult::compute_with_token_name`], but derives the IDs with `scheme`.
+    pub fn compute_with_scheme(
+        scheme: &dyn DerivationScheme,
+        deployer: Address,
+        token_address: Address,
+        decimals: u8,
+        token_name: Option<&str>,
+        hrp: Hrp,
+    ) -> Self {
+        let warp_route_id = scheme.warp_route_id(token_address, deployer);
+        let token_name = token_name.map_or_else(|| scheme.token_name(warp_route_id), str::to_owned);
+        let token_id = scheme.token_id(warp_route_id, &token_name, decimals);
+        Self {
+            deployer,
+            token_address,
+            decimals,
+            warp_route_id,
+            token_name,
+            token_id: format_token_id(token_id, hrp),
+        }
+    }
+}
+
+/// A warp route to derive, configured with builder methods. Defaults to 18 decimals, the
+/// conventional token name, the `token_` prefix and [`SovV1Scheme`].
+///
+/// The deployer and token address are required by [`WarpRoute::new`], so a route missing either
+/// doesn't compile:
+///
+/// ```compile_fail
+/// use sov_warp_utils::{Address, WarpRoute};
+///
+/// let deployer: Address = "0xD2C1bE33A0BcD2007136afD8Ed61CC7561aDa747".parse().unwrap();
+/// let result = WarpRoute::new(deployer).decimals(6).compute();
+/// ```
+#[derive(Clone)]
+pub struct WarpRoute<'a> {
+    deployer: Address,
+    token_address: Address,
+    decimals: u8,
+    token_name: Option<String>,
+    hrp: Hrp,
+    scheme: &'a dyn DerivationScheme,
+}
+
+impl<'a> WarpRoute<'a> {
+    /// The route of `token_address` deployed by `deployer`.
+    pub fn new(deployer: Address, token_address: Address) -> Self {
+        Self {
+            deployer,
+            token_address,
+            decimals: 18,
+            token_name: None,
+            hrp: token_hrp(),
+            scheme: &SovV1Scheme,
+        }
+    }
+
+    /// Sets the number of decimals of the synthetic token.
+    pub fn decimals(mut self, decimals: u8) -> Self {
+        self.decimals = decimals;
+        self
+    }
+
+    /// Hashes `token_name` verbatim instead of the conventional name.
+    pub fn token_name(mut self, token_name: impl Into<String>) -> Self {
+        self.token_name = Some(token_name.into());
+        self
+    }
+
+    /// Sets the prefix the token ID is formatted with.
+    pub fn hrp(mut self, hrp: Hrp) -> Self {
+        self.hrp = hrp;
+        self
+    }
+
+    /// Derives the IDs with `scheme` instead of [`SovV1Scheme`].
+    pub fn scheme(mut self, scheme: &'a dyn DerivationScheme) -> Self {
+        self.scheme = scheme;
+        self
+    }
+
+    /// The warp route ID, without deriving the token ID.
+    pub fn warp_route_id(&self) -> HexHash {
+        self.scheme.warp_route_id(self.token_address, self.deployer)
+    }
+
+    /// Derives the warp route ID and token ID.
+    pub fn compute(&self) -> WarpRouteResult {
+        WarpRouteResult::compute_with_scheme(
+            self.scheme,
+            self.deployer,
+            self.token_address,
+            self.decimals,
+            self.token_name.as_deref(),
+            self.hrp,
+        )
+    }
+}
+
+/// Parses the hex-encoded `deployer` and `token_address` and derives their warp route, formatting
+/// the token ID with the default `token_` prefix.
+pub fn compute_from_strings(
+    deployer: &str,
+    token_address: &str,
+    decimals: u8,
+) -> anyhow::Result<WarpRouteResult> {
+    let deployer: Address = deployer
+        .parse()
+        .map_err(|e| anyhow::anyhow!("Invalid deployer {deployer}, error: {e}"))?;
+    let token_address: Address = token_address
+        .parse()
+        .map_err(|e| anyhow::anyhow!("Invalid token address {token_address}, error: {e}"))?;
+    Ok(WarpRouteResult::compute(
+        deployer,
+        token_address,
+        decimals,
+        token_hrp(),
+    ))
+}
+
+/// Derives the warp route of every `(token_address, decimals)` in `tokens` for `deployer`,
+/// formatting token IDs with `hrp`.
+pub fn compute_many(deployer: Address, tokens: &[(Address, u8)], hrp: Hrp) -> Vec<WarpRouteResult> {
+    tokens
+        .iter()
+        .map(|&(token_address, decimals)| {
+            WarpRouteResult::compute(deployer, token_address, decimals, hrp)
+        })
+        .collect()
+}
+
+/// Like [`compute_many`], but runs the hashing on tokio's blocking thread pool so large batches
+/// don't stall the async runtime.
+#[cfg(feature = "tokio")]
+pub async fn compute_many_async(
+    deployer: Address,
+    tokens: Vec<(Address, u8)>,
+    hrp: Hrp,
+) -> anyhow::Result<Vec<WarpRouteResult>> {
+    tokio::task::spawn_blocking(move || compute_many(deployer, &tokens, hrp))
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to compute warp routes, error: {}", e))
+}
+
+/// Known-answer vectors for the derivation, checked at runtime by the CLI's `selftest` subcommand.
+pub mod test_vectors {
+    use super::{Address, HexHash, WarpRouteResult, token_hrp};
+
+    /// A set of inputs together with the IDs they are expected to produce. Vectors loaded at
+    /// runtime, e.g. exported from a reference implementation, use `TestVector<String>`.
+    #[derive(serde::Deserialize)]
+    pub struct TestVector<S = &'static str> {
+        pub deployer: S,
+        pub token_address: S,
+        pub decimals: u8,
+        pub warp_route_id: S,
+        pub token_id: S,
+    }
+
+    pub const VECTORS: &[TestVector] = &[
+        TestVector {
+            deployer: "0xD2C1bE33A0BcD2007136afD8Ed61CC7561aDa747",
+            token_address: "0x4ed7c70F96B99c776995fB64377f0d4aB3B0e1C1",
+            decimals: 18,
+            warp_route_id: "0x9c081539d40ef7b02d359c5d694e006f0c1130097466cd22d062e07065c6987a",
+            token_id: "token_195zght0wmhcx9j462jtj9lypdua4xw07r6jnjfjsddsmzeh2wsfqrhddvf",
+        },
+        TestVector {
+            deployer: "0xD2C1bE33A0BcD2007136afD8Ed61CC7561aDa747",
+            token_address: "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48",
+            decimals: 6,
+            warp_route_id: "0x3534bf6b1b2cd5d653f9ff0f8d82cb8c6e75ddf292b3d5fbdf98623a88f454fb",
+            token_id: "token_1q9438c85uqmal2qfdy6lzxmjnf46pzgmnt87u04fz300pqs28urq8llxdk",
+        },
+        TestVector {
+            deployer: "0xD2C1bE33A0BcD2007136afD8Ed61CC7561aDa747",
+            token_address: "0x0000000000000000000000000000000000000000",
+            decimals: 0,
+            warp_route_id: "0x56a81936a08fa319c5be26d61186458d567ffbd8599e755deebc9b3abdfb0956",
+            token_id: "token_1ysltwvz58zqyq6hfve3eaz8e23t79slyz407jkl3kyeq9jvqggqql488gq",
+        },
+    ];
+
+    impl<S: AsRef<str>> TestVector<S> {
+        /// Recomputes the vector, failing with the expected and computed values on a mismatch.
+        /// Hex and bech32 values are compared case-insensitively.
+        pub fn check(&self) -> anyhow::Result<()> {
+            let deployer: Address = self.deployer.as_ref().parse()?;
+            let token_address: Address = self.token_address.as_ref().parse()?;
+            let expected_warp_route_id: HexHash = self.warp_route_id.as_ref().parse()?;
+            let result =
+                WarpRouteResult::compute(deployer, token_address, self.decimals, token_hrp());
+            if result.warp_route_id != expected_warp_route_id {
+                anyhow::bail!(
+                    "Warp route ID mismatch: expected {}, computed {}",
+                    expected_warp_route_id,
+                    result.warp_route_id
+                );
+            }
+            if !result.token_id.eq_ignore_ascii_case(self.token_id.as_ref()) {
+                anyhow::bail!(
+                    "Token ID mismatch: expected {}, computed {}",
+                    self.token_id.as_ref(),
+                    result.token_id
+                );
+            }
+            Ok(())
+        }
+    }
+}
+
+/// JavaScript bindings for the derivation, built with `cargo rustc --lib --target
+/// wasm32-unknown-unknown --no-default-features --features wasm --crate-type cdylib` and then
+/// `wasm-bindgen`.
+#[cfg(feature = "wasm")]
+pub mod wasm {
+    use wasm_bindgen::prelude::*;
+
+    /// Computes the warp route for the given hex-encoded deployer and token addresses, returning
+    /// the [`WarpRouteResult`](super::WarpRouteResult) as a JSON string.
+    #[wasm_bindgen]
+    pub fn compute(deployer_hex: &str, token_hex: &str, decimals: u8) -> Result<String, JsError> {
+        let result = super::compute_from_strings(deployer_hex, token_hex, decimals)
+            .map_err(|e| JsError::new(&e.to_string()))?;
+        serde_json::to_string(&result).map_err(|e| JsError::new(&e.to_string()))
+    }
+}
+
+/// C ABI bindings. Every function reads and writes caller-owned, fixed-size buffers, so no
+/// allocation crosses the boundary.
+///
+/// Buffer sizes: addresses are [`ADDRESS_LEN`](ffi::ADDRESS_LEN) (20) bytes, warp route IDs and
+/// token IDs are [`HASH_LEN`](ffi::HASH_LEN) (32) bytes. Each function returns
+/// [`WARP_OK`](ffi::WARP_OK) on success or a negative error code, in which case the output buffer
+/// is left untouched.
+#[cfg(feature = "ffi")]
+pub mod ffi {
+    use std::ffi::c_int;
+
+    /// Length in bytes of an EVM address.
+    pub const ADDRESS_LEN: usize = 20;
+    /// Length in bytes of a warp route ID or token ID.
+    pub const HASH_LEN: usize = 32;
+
+    /// The call succeeded.
+    pub const WARP_OK: c_int = 0;
+    /// A required pointer argument was null.
+    pub const WARP_ERR_NULL_POINTER: c_int = -1;
+    /// The output buffer is too small for the result.
+    pub const WARP_ERR_BUFFER_TOO_SMALL: c_int = -2;
+
+    /// # Safety
+    /// `ptr` must be null or valid for reads of `N` bytes.
+    unsafe fn read<const N: usize>(ptr: *const u8) -> Option<[u8; N]> {
+        // SAFETY: guaranteed by the caller.
+        (!ptr.is_null()).then(|| unsafe { ptr.cast::<[u8; N]>().read_unaligned() })
+    }
+
+    /// Writes the warp route ID for `deployer` and `token` to `out`.
+    ///
+    /// # Safety
+    /// `deployer` and `token` must be valid for reads of [`ADDRESS_LEN`] bytes, and `out` must be
+    /// valid for writes of [`HASH_LEN`] bytes.
+    #[unsafe(no_mangle)]
+    pub unsafe extern "C" fn warp_compute_warp_route_id(
+        deployer: *const u8,
+        token: *const u8,
+        out: *mut u8,
+    ) -> c_int {
+        // SAFETY: guaranteed by the caller.
+        let (Some(deployer), Some(token)) = (unsafe { read(deployer) }, unsafe { read(token) })
+        else {
+            return WARP_ERR_NULL_POINTER;
+        };
+        if out.is_null() {
+            return WARP_ERR_NULL_POINTER;
+        }
+        let id = super::get_warp_route_id(super::HexString(token), super::HexString(deployer));
+        // SAFETY: guaranteed by the caller.
+        unsafe { out.cast::<[u8; HASH_LEN]>().write_unaligned(id.0) };
+        WARP_OK
+    }
+
+    /// Writes the raw token ID for `deployer`, `token` and `decimals` to `out`.
+    ///
+    /// # Safety
+    /// `deployer` and `token` must be valid for reads of [`ADDRESS_LEN`] bytes, and `out` must be
+    /// valid for writes of [`HASH_LEN`] bytes.
+    #[unsafe(no_mangle)]
+    pub unsafe extern "C" fn warp_compute_token_id(
+        deployer: *const u8,
+        token: *const u8,
+        decimals: u8,
+        out: *mut u8,
+    ) -> c_int {
+        let mut route_id = [0; HASH_LEN];
+        // SAFETY: guaranteed by the caller; `route_id` is a local buffer of the right size.
+        let code = unsafe { warp_compute_warp_route_id(deployer, token, route_id.as_mut_ptr()) };
+        if code != WARP_OK {
+            return code;
+        }
+        if out.is_null() {
+            return WARP_ERR_NULL_POINTER;
+        }
+        let id = super::get_token_id(super::HexString(route_id), decimals);
+        // SAFETY: guaranteed by the caller.
+        unsafe { out.cast::<[u8; HASH_LEN]>().write_unaligned(id.0) };
+        WARP_OK
+    }
+
+    /// Writes the bech32m encoding of the token ID at `id`, with the default `token_` prefix, to
+    /// `out` as a NUL-terminated string. The encoding is 65 bytes long (66 with the terminator).
+    ///
+    /// # Safety
+    /// `id` must be valid for reads of [`HASH_LEN`] bytes, and `out` must be valid for writes of
+    /// `out_len` bytes.
+    #[unsafe(no_mangle)]
+    pub unsafe extern "C" fn warp_format_token_id(
+        id: *const u8,
+        out: *mut u8,
+        out_len: usize,
+    ) -> c_int {
+        // SAFETY: guaranteed by the caller.
+        let Some(id) = (unsafe { read(id) }) else {
+            return WARP_ERR_NULL_POINTER;
+        };
+        if out.is_null() {
+            return WARP_ERR_NULL_POINTER;
+        }
+        let encoded = super::format_token_id(super::HexString(id), super::token_hrp());
+        if encoded.len() >= out_len {
+            return WARP_ERR_BUFFER_TOO_SMALL;
+        }
+        // SAFETY: `out` is valid for `out_len` bytes, which covers the string plus its terminator.
+        unsafe {
+            std::ptr::copy_nonoverlapping(encoded.as_ptr(), out, encoded.len());
+            out.add(encoded.len()).write(0);
+        }
+        WARP_OK
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DEPLOYER: &str = "0xD2C1bE33A0BcD2007136afD8Ed61CC7561aDa747";
+
+    #[test]
+    fn vectors_match_derive() {
+        for vector in test_vectors::VECTORS {
+            let deployer: Address = vector.deployer.parse().unwrap();
+            let token_address: Address = vector.token_address.parse().unwrap();
+            let (warp_route_id, token_id) = derive(deployer, token_address, vector.decimals);
+            assert_eq!(warp_route_id, vector.warp_route_id.parse().unwrap());
+            assert_eq!(format_token_id(token_id, token_hrp()), vector.token_id);
+            vector.check().unwrap();
+        }
+    }
+
+    #[test]
+    fn odd_length_address_is_rejected() {
+        // A 40-digit address with one digit too many.
+        let error = Address::from_str(&format!("{DEPLOYER}7")).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "hex string has odd number of digits (41)"
+        );
+    }
+
+    #[test]
+    fn invalid_characters_are_reported_with_their_offset() {
+        let input = "0x4ed7c70F96B99c776995zB64377f0d4aB3B0e1C1";
+        let error = Address::from_str(input).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Failed to decode hex string 0x4ed7c7...B3B0e1C1, error: invalid character 'z' at offset 22"
+        );
+        assert_eq!(input.as_bytes()[22], b'z');
+    }
+
+    #[test]
+    fn parse_exact_names_the_expected_length() {
+        let hash = HexString::parse_exact::<32>(test_vectors::VECTORS[0].warp_route_id).unwrap();
+        assert_eq!(
+            hash,
+            test_vectors::VECTORS[0].warp_route_id.parse().unwrap()
+        );
+        let error = HexString::parse_exact::<32>(DEPLOYER).unwrap_err();
+        assert_eq!(error.to_string(), "Expected 32 bytes of hex, got 20");
+    }
+
+    #[test]
+    fn decimal_addresses_parse_to_their_big_endian_bytes() {
+        let address = address_from_decimal("1203208674598707348246046875766576982950425765703");
+        assert_eq!(address.unwrap(), DEPLOYER.parse().unwrap());
+        let prefixed = address_from_decimal("0d450113280991427438772068108049397855241037210049");
+        assert_eq!(
+            prefixed.unwrap(),
+            "0x4ed7c70F96B99c776995fB64377f0d4aB3B0e1C1"
+                .parse()
+                .unwrap()
+        );
+        assert_eq!(address_from_decimal("0").unwrap(), HexString([0; 20]));
+        // 2^160 - 1 is the largest address, and 2^160 doesn't fit.
+        let max = address_from_decimal("1461501637330902918203684832716283019655932542975");
+        assert_eq!(max.unwrap(), HexString([0xff; 20]));
+        let error = address_from_decimal("1461501637330902918203684832716283019655932542976");
+        assert!(
+            error
+                .unwrap_err()
+                .to_string()
+                .contains("doesn't fit in 20 bytes")
+        );
+        assert!(address_from_decimal("").is_err());
+        assert!(address_from_decimal("12a").is_err());
+    }
+
+    #[test]
+    fn group_ids_ignore_order_and_duplicates() {
+        let ids: Vec<HexHash> = test_vectors::VECTORS
+            .iter()
+            .map(|vector| parse_token_id(vector.token_id).unwrap())
+            .collect();
+        assert!(ids.len() > 1);
+        let group_id = get_group_id(&ids);
+        let mut reversed = ids.clone();
+        reversed.reverse();
+        assert_eq!(get_group_id(&reversed), group_id);
+        let mut duplicated = ids.clone();
+        duplicated.push(ids[0]);
+        duplicated.insert(0, ids[1]);
+        assert_eq!(get_group_id(&duplicated), group_id);
+
+        let mut sorted = ids.clone();
+        sorted.sort();
+        let concatenated: Vec<u8> = sorted.iter().flat_map(|id| id.0).collect();
+        assert_eq!(group_id, HexString(Sha256::digest(concatenated).into()));
+        assert_ne!(get_group_id(&ids[1..]), group_id);
+    }
+
+    #[test]
+    fn warp_route_builder_matches_derive() {
+        for vector in test_vectors::VECTORS {
+            let deployer: Address = vector.deployer.parse().unwrap();
+            let token_address: Address = vector.token_address.parse().unwrap();
+            let (warp_route_id, token_id) = derive(deployer, token_address, vector.decimals);
+            let route = WarpRoute::new(deployer, token_address).decimals(vector.decimals);
+            assert_eq!(route.warp_route_id(), warp_route_id);
+            let result = route.compute();
+            assert_eq!(result.warp_route_id, warp_route_id);
+            assert_eq!(result.token_id, format_token_id(token_id, token_hrp()));
+            assert_eq!(result.token_name, get_token_name(warp_route_id));
+        }
+        let deployer: Address = DEPLOYER.parse().unwrap();
+        let route = WarpRoute::new(deployer, NATIVE_TOKEN_ADDRESS);
+        let (warp_route_id, token_id) = derive(deployer, NATIVE_TOKEN_ADDRESS, 18);
+        let named = route.clone().token_name("Ether").hrp(group_hrp()).compute();
+        assert_eq!(named.warp_route_id, warp_route_id);
+        assert_eq!(named.token_name, "Ether");
+        let expected = get_token_id_with_name(warp_route_id, "Ether", 18);
+        assert_eq!(named.token_id, format_token_id(expected, group_hrp()));
+        assert_ne!(expected, token_id);
+    }
+
+    #[test]
+    fn compute_from_strings_names_the_missing_or_invalid_field() {
+        let token_address = test_vectors::VECTORS[0].token_address;
+        let Err(error) = compute_from_strings("", token_address, 18) else {
+            panic!("an empty deployer derived a route");
+        };
+        assert!(
+            error.to_string().starts_with("Invalid deployer , error:"),
+            "{error}"
+        );
+        let Err(error) = compute_from_strings(DEPLOYER, "0xabcd", 18) else {
+            panic!("a short token address derived a route");
+        };
+        assert!(
+            error
+                .to_string()
+                .starts_with("Invalid token address 0xabcd"),
+            "{error}"
+        );
+        let result = compute_from_strings(DEPLOYER, token_address, 18).unwrap();
+        assert_eq!(result.token_id, test_vectors::VECTORS[0].token_id);
+    }
+
+    #[test]
+    fn try_into_fixed_checks_the_length() {
+        let bytes = HexString(vec![0xab; 20]);
+        let address: Address = bytes.clone().try_into_fixed().unwrap();
+        assert_eq!(address, HexString([0xab; 20]));
+        let error = bytes.try_into_fixed::<32>().unwrap_err();
+        assert_eq!(error.to_string(), "Expected 32 bytes of hex, got 20");
+    }
+
+    #[test]
+    fn every_path_derives_the_same_ids() {
+        let deployer: Address = DEPLOYER.parse().unwrap();
+        let token_address: Address = test_vectors::VECTORS[1].token_address.parse().unwrap();
+        let (warp_route_id, token_id) = derive(deployer, token_address, 6);
+        assert_eq!(warp_route_id, get_warp_route_id(token_address, deployer));
+        assert_eq!(token_id, get_token_id(warp_route_id, 6));
+        let token_name = get_token_name(warp_route_id);
+        assert_eq!(
+            derive_reference(deployer, token_address, 6, &token_name),
+            (warp_route_id, token_id)
+        );
+        let hashed = derive_with_hash_fn(deployer, token_address, 6, get_token_name, |preimage| {
+            Ok::<_, ()>(Sha256::digest(preimage).into())
+        });
+        assert_eq!(hashed, Ok((warp_route_id, token_name.clone(), token_id)));
+        assert_eq!(
+            UnpaddedScheme.token_id(warp_route_id, &token_name, 6),
+            SovV1Scheme.token_id(warp_route_id, &token_name, 6)
+        );
+        let preimage = preimage::token_id(warp_route_id, &token_name, 6);
+        let mut expected: [u8; 32] = Sha256::digest(preimage).into();
+        expected[31] = 6;
+        assert_eq!(token_id, HexString(expected));
+    }
+
+    #[test]
+    fn increment_carries_and_overflows() {
+        let mut bytes = [0u8; 20];
+        bytes[19] = 0xff;
+        let mut expected = [0u8; 20];
+        expected[18] = 0x01;
+        assert_eq!(HexString(bytes).increment(), Some(HexString(expected)));
+        assert_eq!(
+            NATIVE_TOKEN_ADDRESS
+                .increment()
+                .unwrap()
+                .to_trimmed_string(),
+            "0x01"
+        );
+        assert_eq!(HexString([0xff; 20]).increment(), None);
+    }
+
+    #[test]
+    fn derivation_uses_the_protocol_constants() {
+        let deployer: Address = DEPLOYER.parse().unwrap();
+        let token_address: Address = test_vectors::VECTORS[0].token_address.parse().unwrap();
+        let preimage = [
+            &pad_address_to_32(token_address)[..],
+            &[SEPARATOR_BYTE],
+            &deployer.0,
+        ]
+        .concat();
+        let warp_route_id = HexString(Sha256::digest(preimage).into());
+        assert_eq!(warp_route_id, get_warp_route_id(token_address, deployer));
+        assert_eq!(
+            get_token_name(warp_route_id),
+            format!("{TOKEN_NAME_PREFIX}{warp_route_id}")
+        );
+        assert_eq!(SEPARATOR_BYTE, 0x00);
+        assert_eq!(TOKEN_NAME_PREFIX, "Synthetic token for ");
+    }
+
+    #[test]
+    fn padding_keeps_leading_zeros() {
+        let address: Address = "0x0000000000000000000000000000000000000abc"
+            .parse()
+            .unwrap();
+        let mut expected = [0u8; 32];
+        expected[30..].copy_from_slice(&[0x0a, 0xbc]);
+        assert_eq!(pad_address_to_32(address), expected);
+    }
+
+    #[test]
+    fn token_id_payload_length_is_checked() {
+        let encode = |len: usize| bech32::encode::<Bech32m>(token_hrp(), &vec![7; len]).unwrap();
+        let error = parse_token_id(&encode(31)).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Token ID payload too short: expected 32 bytes, got 31"
+        );
+        let error = parse_token_id(&encode(33)).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Token ID payload too long: expected 32 bytes, got 33"
+        );
+        assert_eq!(parse_token_id(&encode(32)).unwrap(), HexString([7; 32]));
+    }
+
+    #[test]
+    fn token_id_preimage_ends_in_the_decimals_byte() {
+        let warp_route_id = HexString([1; 32]);
+        let preimage = preimage::token_id(warp_route_id, "name", 6);
+        assert_eq!(preimage.len(), 32 + 4 + 1);
+        assert_eq!(preimage[32..36], *b"name");
+        assert_eq!(preimage[36..], [6]);
+    }
+
+    #[test]
+    fn trimmed_strings_keep_at_least_one_byte() {
+        let address: Address = "0x0001000000000000000000000000000000000abc"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            address.to_trimmed_string(),
+            "0x01000000000000000000000000000000000abc"
+        );
+        assert_eq!(NATIVE_TOKEN_ADDRESS.to_trimmed_string(), "0x00");
+        assert_eq!(HexString(Vec::new()).to_trimmed_string(), "0x");
+    }
+
+    #[test]
+    fn malformed_hex_is_an_error() {
+        let huge = "ab".repeat(1 << 20);
+        let corpus = [
+            "",
+            "0x",
+            "0",
+            "0x0",
+            "x",
+            "0X4ed7c70F96B99c776995fB64377f0d4aB3B0e1C1",
+            " 0x4ed7c70F96B99c776995fB64377f0d4aB3B0e1C1",
+            "0x4ed7c70F96B99c776995fB64377f0d4aB3B0e1C",
+            "0x4ed7c70F96B99c776995fB64377f0d4aB3B0e1C1C1",
+            "0x4ed7c70F96B99c776995fB64377f0d4aB3B0e1Cg",
+            "é",
+            "éé",
+            "0x🦀🦀",
+            "4ed7c70F96B99c776995fB64377f0d4aB3B0e1é",
+            "\0\0",
+            &huge,
+        ];
+        for input in corpus {
+            assert!(Address::from_str(input).is_err(), "{input:?} parsed");
+            assert!(HexHash::from_str(input).is_err(), "{input:?} parsed");
+        }
+    }
+
+    #[test]
+    fn debug_matches_display() {
+        let address: Address = DEPLOYER.parse().unwrap();
+        assert_eq!(format!("{address:?}"), format!("{address}"));
+        let hash = get_warp_route_id(address, address);
+        assert_eq!(format!("{hash:?}"), format!("{hash}"));
+    }
+
+    #[test]
+    fn ordering_is_lexicographic_over_the_bytes() {
+        let parse = |s: &str| -> HexString { s.parse().unwrap() };
+        let (one, one_zero, two) = (parse("0x01"), parse("0x0100"), parse("0x02"));
+        assert!(one < one_zero && one_zero < two);
+
+        let map = std::collections::BTreeMap::from([(two, 'c'), (one_zero, 'b'), (one, 'a')]);
+        let keys: Vec<_> = map.keys().map(ToString::to_string).collect();
+        assert_eq!(keys, ["0x01", "0x0100", "0x02"]);
+        assert_eq!(map.values().collect::<String>(), "abc");
+    }
+
+    #[test]
+    fn maps_can_be_searched_by_byte_slices() {
+        let address: Address = DEPLOYER.parse().unwrap();
+        let addresses = std::collections::HashMap::from([(address, "deployer")]);
+        let bytes: &[u8] = &address.0;
+        assert_eq!(addresses.get(bytes), Some(&"deployer"));
+        assert_eq!(addresses.get(&bytes[1..]), None);
+
+        let dynamic = std::collections::BTreeSet::from([HexString(vec![1, 2]), HexString(vec![3])]);
+        assert!(dynamic.contains(&[1u8, 2][..]));
+        assert!(!dynamic.contains(&[1u8][..]));
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn compute_many_async_matches_compute_many() {
+        let deployer: Address = DEPLOYER.parse().unwrap();
+        let tokens: Vec<_> = test_vectors::VECTORS
+            .iter()
+            .map(|vector| (vector.token_address.parse().unwrap(), vector.decimals))
+            .collect();
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        let results = runtime
+            .block_on(compute_many_async(deployer, tokens.clone(), token_hrp()))
+            .unwrap();
+        let expected = compute_many(deployer, &tokens, token_hrp());
+        let token_ids = |results: &[WarpRouteResult]| -> Vec<String> {
+            results
+                .iter()
+                .map(|result| result.token_id.clone())
+                .collect()
+        };
+        assert_eq!(token_ids(&results), token_ids(&expected));
+        let vector_ids: Vec<_> = test_vectors::VECTORS.iter().map(|v| v.token_id).collect();
+        assert_eq!(token_ids(&results), vector_ids);
+    }
+
+    /// Calls the exported `compute` natively. Only the success path is covered, since building a
+    /// `JsError` needs a JavaScript host.
+    #[cfg(feature = "wasm")]
+    #[test]
+    fn wasm_compute_returns_the_result_as_json() {
+        for vector in test_vectors::VECTORS {
+            let json = wasm::compute(vector.deployer, vector.token_address, vector.decimals)
+                .unwrap_or_else(|_| panic!("failed to compute {}", vector.token_address));
+            let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+            assert_eq!(value["warp_route_id"], vector.warp_route_id);
+            assert_eq!(value["token_id"], vector.token_id);
+            assert_eq!(value["decimals"], vector.decimals);
+        }
+    }
+
+    #[cfg(feature = "ffi")]
+    #[test]
+    fn ffi_functions_match_the_library_and_report_errors() {
+        use ffi::*;
+        use std::ptr::{null, null_mut};
+
+        let vector = &test_vectors::VECTORS[0];
+        let deployer: Address = vector.deployer.parse().unwrap();
+        let token: Address = vector.token_address.parse().unwrap();
+        let (d, t) = (deployer.0.as_ptr(), token.0.as_ptr());
+        let mut route_id = [0u8; HASH_LEN];
+        let mut token_id = [0u8; HASH_LEN];
+        // SAFETY: every pointer is null or points to a buffer of the documented size.
+        unsafe {
+            assert_eq!(
+                warp_compute_warp_route_id(d, t, route_id.as_mut_ptr()),
+                WARP_OK
+            );
+            let code = warp_compute_token_id(d, t, vector.decimals, token_id.as_mut_ptr());
+            assert_eq!(code, WARP_OK);
+
+            let mut untouched = [0xaa; HASH_LEN];
+            let out = untouched.as_mut_ptr();
+            assert_eq!(
+                warp_compute_warp_route_id(null(), t, out),
+                WARP_ERR_NULL_POINTER
+            );
+            assert_eq!(
+                warp_compute_warp_route_id(d, null(), out),
+                WARP_ERR_NULL_POINTER
+            );
+            assert_eq!(
+                warp_compute_warp_route_id(d, t, null_mut()),
+                WARP_ERR_NULL_POINTER
+            );
+            assert_eq!(
+                warp_compute_token_id(null(), t, 18, out),
+                WARP_ERR_NULL_POINTER
+            );
+            assert_eq!(
+                warp_compute_token_id(d, t, 18, null_mut()),
+                WARP_ERR_NULL_POINTER
+            );
+            assert_eq!(untouched, [0xaa; HASH_LEN]);
+        }
+        assert_eq!(HexString(route_id), vector.warp_route_id.parse().unwrap());
+
+        let mut encoded = [0xaau8; 66];
+        // SAFETY: as above, with `encoded` valid for the lengths passed.
+        unsafe {
+            let id = token_id.as_ptr();
+            let out = encoded.as_mut_ptr();
+            assert_eq!(warp_format_token_id(null(), out, 66), WARP_ERR_NULL_POINTER);
+            assert_eq!(
+                warp_format_token_id(id, null_mut(), 66),
+                WARP_ERR_NULL_POINTER
+            );
+            // 65 bytes leave no room for the terminator.
+            assert_eq!(warp_format_token_id(id, out, 65), WARP_ERR_BUFFER_TOO_SMALL);
+            assert_eq!(encoded, [0xaa; 66]);
+            assert_eq!(warp_format_token_id(id, out, 66), WARP_OK);
+        }
+        let formatted = std::ffi::CStr::from_bytes_until_nul(&encoded).unwrap();
+        assert_eq!(formatted.to_str().unwrap(), vector.token_id);
+    }
+
+    #[test]
+    fn addresses_convert_to_left_padded_hashes() {
+        let address: Address = "0xabcd000000000000000000000000000000001234"
+            .parse()
+            .unwrap();
+        let padded = HexHash::from(address);
+        assert_eq!(
+            padded.to_string(),
+            "0x000000000000000000000000abcd000000000000000000000000000000001234"
+        );
+        assert_eq!(padded.0, pad_address_to_32(address));
+        assert_eq!(padded.0, address.to_h256());
+        assert_eq!(preimage::warp_route_id(address, address)[..32], padded.0);
+    }
+
+    #[test]
+    fn h256_matches_hyperlane_bytes32() {
+        // Hyperlane's `addressToBytes32` of its Ethereum mailbox.
+        let mailbox: Address = "0xc005dc82818d67AF737725bD4bf75435d065D239"
+            .parse()
+            .unwrap();
+        let bytes32 = HexString::parse_exact::<32>(
+            "0x000000000000000000000000c005dc82818d67af737725bd4bf75435d065d239",
+        )
+        .unwrap();
+        assert_eq!(mailbox.to_h256(), bytes32.0);
+        assert_eq!(Address::from_h256(bytes32.0).unwrap(), mailbox);
+        assert_eq!(HexHash::from_h256(bytes32.0).to_h256(), bytes32.0);
+
+        let mut dirty = bytes32.0;
+        dirty[0] = 1;
+        let error = Address::from_h256(dirty).unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("the first 12 bytes are not zero")
+        );
+    }
+
+    #[test]
+    fn serde_round_trips_as_hex_and_bytes() {
+        let address: Address = DEPLOYER.parse().unwrap();
+
+        let json = serde_json::to_string(&address).unwrap();
+        assert_eq!(json, format!("\"{}\"", DEPLOYER.to_lowercase()));
+        assert_eq!(serde_json::from_str::<Address>(&json).unwrap(), address);
+
+        let binary = bincode::serialize(&address).unwrap();
+        // A u64 length, then the raw bytes.
+        assert_eq!(binary[..8], 20u64.to_le_bytes());
+        assert_eq!(binary[8..], address.0);
+        assert_eq!(bincode::deserialize::<Address>(&binary).unwrap(), address);
+    }
+
+    #[test]
+    fn yaml_round_trips_as_hex() {
+        let address: Address = DEPLOYER.parse().unwrap();
+        let entries =
+            std::collections::BTreeMap::from([(address, get_warp_route_id(address, address))]);
+        let yaml = serde_yaml::to_string(&entries).unwrap();
+        assert_eq!(
+            yaml,
+            format!("{}: {}\n", DEPLOYER.to_lowercase(), entries[&address])
+        );
+        assert_eq!(
+            serde_yaml::from_str::<std::collections::BTreeMap<Address, HexHash>>(&yaml).unwrap(),
+            entries
+        );
+    }
+
+    #[test]
+    fn prefixed_and_bare_json_hex_are_equal() {
+        let prefixed: HexString = serde_json::from_str("\"0xabcd\"").unwrap();
+        let bare: HexString = serde_json::from_str("\"abcd\"").unwrap();
+        assert_eq!(prefixed, bare);
+        assert_eq!(prefixed.0, [0xab, 0xcd]);
+        let bare: Address = serde_json::from_value(DEPLOYER[2..].into()).unwrap();
+        assert_eq!(bare, DEPLOYER.parse().unwrap());
+    }
+
+    #[test]
+    fn oversized_byte_sequences_are_rejected() {
+        let declared = (MAX_DESERIALIZED_LEN as u64 + 1).to_le_bytes();
+        let error = bincode::deserialize::<HexString>(&declared).unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("a sequence of at most 1048576 bytes"),
+            "{error}"
+        );
+
+        #[derive(Debug, serde::Deserialize)]
+        struct Small {
+            #[serde(deserialize_with = "hex_string_serde::deserialize_bounded::<4, _, _>")]
+            bytes: Vec<u8>,
+        }
+        let small = bincode::serialize(&HexString(vec![1, 2, 3, 4])).unwrap();
+        assert_eq!(
+            bincode::deserialize::<Small>(&small).unwrap().bytes,
+            [1, 2, 3, 4]
+        );
+        let large = bincode::serialize(&HexString(vec![1, 2, 3, 4, 5])).unwrap();
+        let error = bincode::deserialize::<Small>(&large).unwrap_err();
+        assert!(
+            error.to_string().contains("a sequence of at most 4 bytes"),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn wrong_length_json_hex_is_rejected() {
+        let error = serde_json::from_str::<Address>("\"0xabcd\"").unwrap_err();
+        assert!(error.to_string().contains("Invalid hex string length"));
+    }
+}
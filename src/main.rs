@@ -1,192 +1,2968 @@
-use bech32::{Bech32m, Hrp};
-use clap::Parser;
+use bech32::Hrp;
+use clap::{CommandFactory, Parser, ValueEnum};
 use sha2::{Digest, Sha256};
-use std::{fmt::Display, str::FromStr};
+use std::{
+    fmt::{Display, Write},
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
 
-use borsh::{BorshDeserialize, BorshSerialize};
-
-#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
-pub struct HexString<T = Vec<u8>>(pub T)
-where
-    T: AsRef<[u8]>;
-
-type Address = HexString<[u8; 20]>;
-type HexHash = HexString<[u8; 32]>;
+use sov_warp_utils::{
+    Address, DerivationScheme, HexHash, HexString, NATIVE_TOKEN_ADDRESS, SEPARATOR_BYTE,
+    SovV1Scheme, TOKEN_NAME_PREFIX, UnpaddedScheme, WarpRouteResult, address_from_decimal, derive,
+    derive_reference, derive_with_hash_fn, format_token_id, get_group_id, get_token_id,
+    get_token_id_with_name, get_token_name, get_warp_route_id, group_hrp, parse_token_id,
+    test_vectors, token_hrp,
+};
 
 #[derive(clap::Parser)]
-/// Computes the warp route ID and token ID for a warp route mapping native Ether from an EVM chain
-/// to a sovereign SDK chain.
-
+/// Computes the warp route ID and token ID for a warp route mapping a native asset or ERC-20 from
+/// an EVM chain to a sovereign SDK chain.
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 struct Args {
-    #[clap(long, short)]
+    #[command(subcommand)]
+    command: Option<Command>,
+    #[command(flatten)]
+    derive: DeriveArgs,
+}
+
+/// The flags of the `derive` subcommand, which are also accepted without a subcommand.
+#[derive(clap::Args)]
+struct DeriveArgs {
+    /// An address, hash or `token_...` ID to inspect instead of computing a warp route
+    #[clap(conflicts_with_all = ["deployer", "token_address", "token_list", "batch", "config", "hrp", "remote_domain", "format", "validate_only"])]
+    value: Option<String>,
+    /// A JSON or YAML file setting `deployer` and `token-address`. May be repeated, in which case
+    /// later files override earlier ones. Flags take precedence over every file
+    #[clap(long)]
+    config: Vec<PathBuf>,
+    /// The format of every `--config`. Detected from each file's extension if omitted
+    #[clap(long, value_enum, requires = "config")]
+    config_format: Option<ConfigFormat>,
+    #[clap(long, short, required_unless_present_any = ["value", "config", "update", "print_config"])]
     /// The address that will be used to deploy the warp route on the Sovereign SDK chain
-    deployer: Address,
+    deployer: Option<String>,
     /// The ethereum address of the wrapped token on the EVM chain
+    #[clap(long, short, required_unless_present_any = ["value", "token_list", "hyperlane_config", "from_receipt", "batch", "config", "update", "print_config", "native", "route_type"], required_if_eq("route_type", "collateral"))]
+    token_address: Option<String>,
+    /// Use the `contractAddress` of this Ethereum transaction receipt JSON, or `-` for stdin, as
+    /// the token address. Either a bare receipt or a JSON-RPC response wrapping it in `result`
+    #[clap(long, conflicts_with_all = ["value", "token_address", "token_list", "hyperlane_config", "batch"])]
+    from_receipt: Option<PathBuf>,
+    /// Compute the route of the chain's native asset, which has no token contract. The zero
+    /// address is hashed in place of a token address. The same as `--route-type native`
+    #[clap(long, conflicts_with_all = ["value", "token_address", "token_list", "batch", "from_receipt", "config"])]
+    native: bool,
+    /// What the EVM side of the route locks or mints, which decides the address that is hashed.
+    /// Defaults to `collateral`, which requires `--token-address`. Synthetic routes wrap no EVM
+    /// token, so they're out of scope
+    #[clap(long, value_enum, conflicts_with_all = ["value", "token_list", "hyperlane_config", "batch", "from_receipt", "config", "native"])]
+    route_type: Option<RouteType>,
+    /// How `--deployer` and `--token-address` are interpreted
+    #[clap(long, value_enum, default_value_t = InputFormat::Hex)]
+    input_format: InputFormat,
+    /// A Uniswap-style token list JSON file, or `-` for stdin. Computes a warp route for every token in the list
+    /// using the shared `--deployer`, printing the results as a JSON array in the same order
+    #[clap(long, conflicts_with = "token_address")]
+    token_list: Option<PathBuf>,
+    /// A Hyperlane warp route config, as YAML or JSON, or `-` for stdin. Computes a warp route for
+    /// every EVM token in its `tokens` list using the shared `--deployer`, printed like a
+    /// `--token-list`
+    #[clap(long, conflicts_with_all = ["value", "token_address", "token_list", "batch", "native", "assert_warp_route_id", "assert_token_id", "explain", "explain_json"])]
+    hyperlane_config: Option<PathBuf>,
+    /// A file, or `-` for stdin, with one `token_address[,decimals]` line per token. Computes a
+    /// warp route for each line using the shared `--deployer`, printing results as they are
+    /// computed. Decimals default to 18; blank lines and lines starting with `#` are ignored
+    #[clap(long, conflicts_with_all = ["token_address", "token_list", "output"])]
+    batch: Option<PathBuf>,
+    /// Skip this many `--batch` entries before computing any
+    #[clap(long, requires = "batch", default_value_t = 0)]
+    skip: usize,
+    /// Compute at most this many `--batch` entries, after `--skip`
+    #[clap(long, requires = "batch")]
+    take: Option<usize>,
+    /// Print `--token-list` and `--batch` results sorted by token address instead of in input
+    /// order. Batch results are then printed at the end instead of as they are computed
+    #[clap(long)]
+    sort_output: bool,
+    /// Print to stderr how long parsing, deriving and printing `--batch` entries took, in total
+    /// and per entry
+    #[clap(long, requires = "batch")]
+    timings: bool,
+    /// Print `--batch` results as a single JSON array instead of one JSON object per line
+    #[clap(long, requires = "batch", conflicts_with = "format")]
+    json_array: bool,
+    /// Stop at the first `--batch` line that fails to parse instead of skipping it
+    #[clap(long, requires = "batch", conflicts_with = "continue_on_error")]
+    fail_fast: bool,
+    /// Report and skip `--batch` lines that fail to parse, failing at the end. This is the
+    /// default
+    #[clap(long, requires = "batch")]
+    continue_on_error: bool,
+    /// Abort without printing anything if a batch input has more than this many entries
+    #[clap(long)]
+    max_batch_size: Option<usize>,
+    /// The bech32 prefix used for the token ID. Any `{domain}` placeholder is replaced with
+    /// the value of `--remote-domain`
+    #[clap(long, default_value = "token_")]
+    hrp: String,
+    /// The comma-separated bech32 prefixes accepted when inspecting a `VALUE`. Any other bech32
+    /// value, such as a Cosmos address, is rejected instead of being decoded
+    #[clap(long, value_delimiter = ',', value_parser = parse_hrp, default_value = "token_")]
+    hrp_allowlist: Vec<Hrp>,
+    /// The Hyperlane domain ID of the EVM chain, substituted into `--hrp`. Pass a
+    /// comma-separated list to compute one result per domain
+    #[clap(long, value_delimiter = ',')]
+    remote_domain: Vec<u32>,
+    /// The format used to print the result. Defaults to `json` for `--token-list`, `csv` for
+    /// `--batch` and `text` otherwise
+    #[clap(long, value_enum)]
+    format: Option<OutputFormat>,
+    /// Write the result to this file in `--format`, while still printing it as text to stdout
+    #[clap(long)]
+    output: Option<PathBuf>,
+    /// Fail instead of overwriting `--output` if it already exists
+    #[clap(long, requires = "output")]
+    no_clobber: bool,
+    /// Print only the warp route ID and token ID of each result, one per line, without labels,
+    /// warnings or the batch summary. Other formats are printed in full, so CSV keeps its header
+    /// row; only the warnings and summary are silenced. With `--output`, don't print the text copy
+    /// to stdout at all. Errors are still printed
     #[clap(long, short)]
-    token_address: Address,
+    quiet: bool,
+    /// Also write every result to this deployment manifest, along with the tool version and the
+    /// generation time. The manifest is YAML if the path ends in `.yaml` or `.yml`, JSON otherwise
+    #[clap(long, conflicts_with = "validate_only")]
+    manifest: Option<PathBuf>,
+    /// Recompute the entries of an existing `--manifest` whose stored IDs no longer match their
+    /// stored inputs, e.g. after editing an entry's decimals, and write it back in place. Other
+    /// entries are left untouched
+    #[clap(long, conflicts_with_all = ["value", "config", "deployer", "token_address", "token_list", "batch", "manifest", "output", "format", "validate_only"])]
+    update: Option<PathBuf>,
+    /// Only check that the inputs parse, printing nothing and exiting with a nonzero code if
+    /// they don't
+    #[clap(long)]
+    validate_only: bool,
+    /// Don't warn when the deployer and token address are the same
+    #[clap(long)]
+    allow_same: bool,
+    #[command(flatten)]
+    decimals_check: DecimalsCheck,
+    /// The number of decimals of the synthetic token on the Sovereign SDK chain, which is hashed
+    /// into the token ID. Lists and batches give each token's decimals instead
+    #[clap(long, default_value_t = 18, conflicts_with_all = ["token_list", "hyperlane_config", "batch"])]
+    local_decimals: u8,
+    /// The number of decimals of the token on the EVM chain. It isn't hashed, and only warns
+    /// when it exceeds `--local-decimals`, since transfers would then lose precision
+    #[clap(long, conflicts_with_all = ["token_list", "hyperlane_config", "batch"])]
+    remote_decimals: Option<u8>,
+    /// Don't warn about addresses ending in several zero bytes, which often means they were
+    /// serialized in the wrong byte order
+    #[clap(long)]
+    allow_trailing_zeros: bool,
+    /// Resolve a `--token-address` containing a `.`, such as `usdc.eth`, as an ENS name using
+    /// this Ethereum JSON-RPC endpoint
+    #[cfg(feature = "ens")]
+    #[clap(long)]
+    resolve_ens: Option<String>,
+    /// Hash this token name verbatim instead of the conventional `Synthetic token for ...` name.
+    /// This changes the token ID
+    #[clap(long, conflicts_with = "token_list")]
+    token_name: Option<String>,
+    /// Append this string to the token name, conventional or `--token-name`, before hashing it.
+    /// The hashed name is `{token name}{salt}` with no separator. This changes the token ID
+    #[clap(long)]
+    name_salt: Option<String>,
+    /// The derivation scheme used to compute the IDs
+    #[clap(long, value_enum, default_value_t = Scheme::SovV1)]
+    scheme: Scheme,
+    /// Print the tool version and the derivation parameters in effect for `--scheme`, `--hrp`,
+    /// `--token-name` and `--name-salt` as JSON, without computing anything
+    #[clap(long)]
+    print_config: bool,
+    /// Also print the token address left-padded to 32 bytes, as hashed into the warp route ID.
+    /// This matches Hyperlane's `bytes32` representation of the token
+    #[clap(long)]
+    show_padded_token: bool,
+    /// Also print the token ID as 32 bytes of hex, next to its bech32m form, in `text` and `csv`
+    /// output. The `json` and `yaml` formats always include it as `token_id_hex`
+    #[clap(long)]
+    show_token_id_hex: bool,
+    /// Omit leading zero bytes when printing addresses. This only affects display
+    #[clap(long)]
+    trim_leading_zeros: bool,
+    /// Also print each result's decimals in `text` output. Every other format always includes
+    /// them
+    #[clap(long)]
+    show_decimals: bool,
+    /// Print token IDs in `text` output with their data split into space-separated groups of 4
+    /// characters. This is for reading aloud or transcribing only; it can't be decoded
+    #[clap(long)]
+    group_bech32: bool,
+    /// The character printed between `--group-bech32` groups instead of a space, e.g. `-`. Letters
+    /// and digits are rejected, since they would read as part of the token ID
+    #[clap(long, requires = "group_bech32", value_parser = parse_group_separator)]
+    group_separator: Option<char>,
+    /// Print token IDs without their prefix and `1` separator, i.e. only the bech32 data and
+    /// checksum. This is non-canonical and can't be decoded on its own; it only affects display
+    #[clap(long, conflicts_with = "group_bech32")]
+    bech32_data_only: bool,
+    /// Shorten addresses and warp route IDs in `markdown` output to their first and last 4 bytes.
+    /// This only affects display
+    #[clap(long)]
+    short: bool,
+    /// Fail, explaining the difference, unless the warp route ID matches this hex or bech32 value.
+    /// The decoded bytes are compared, so case doesn't matter
+    #[clap(long, value_parser = parse_hash_or_bech32, conflicts_with_all = ["token_list", "batch"])]
+    assert_warp_route_id: Option<HexHash>,
+    /// Fail, explaining the difference, unless the token ID matches this hex or bech32 value.
+    /// The decoded bytes are compared, so case doesn't matter
+    #[clap(long, value_parser = parse_hash_or_bech32, conflicts_with_all = ["token_list", "batch"])]
+    assert_token_id: Option<HexHash>,
+    /// Print each step of the derivation, with the bytes it hashes or produces, instead of the
+    /// result
+    #[clap(long, conflicts_with_all = ["token_list", "batch", "format", "output", "manifest", "scheme", "token_name", "name_salt"])]
+    explain: bool,
+    /// Like `--explain`, but print the steps as a JSON array of `{step, description, bytes}`
+    /// objects
+    #[clap(long, conflicts_with_all = ["explain", "token_list", "batch", "format", "output", "manifest", "scheme", "token_name", "name_salt"])]
+    explain_json: bool,
+    /// Recompute every result with a reference implementation, which hashes each full preimage
+    /// at once, and fail if the two disagree
+    #[clap(long, conflicts_with = "scheme")]
+    double_check: bool,
+    /// Delegate SHA-256 to this shell command, e.g. one backed by an HSM. Each preimage is
+    /// written to its stdin, and the first word of its stdout must be the 32-byte digest as hex
+    #[clap(long, conflicts_with_all = ["scheme", "explain", "explain_json"])]
+    hash_cmd: Option<String>,
+}
+
+/// The kind of Hyperlane token on the EVM side of a warp route.
+#[derive(Copy, Clone, clap::ValueEnum)]
+enum RouteType {
+    /// The chain's native asset, hashed as [`NATIVE_TOKEN_ADDRESS`]
+    Native,
+    /// An ERC-20 locked by the route, hashed as its `--token-address`
+    Collateral,
+}
+
+/// How address inputs are interpreted. An explicit `0x` prefix always means hex, and outside of
+/// `hex` an explicit `0d` prefix always means decimal.
+#[derive(Copy, Clone, clap::ValueEnum)]
+enum InputFormat {
+    /// Hex, with or without a `0x` prefix
+    Hex,
+    /// A decimal integer, with or without a `0d` prefix
+    Decimal,
+    /// Decimal for unprefixed digit-only values that aren't 40 digits long, hex otherwise
+    Auto,
+}
+
+impl InputFormat {
+    fn parse_address(self, s: &str) -> anyhow::Result<Address> {
+        if s.starts_with("0x") {
+            return HexString::parse_exact(s);
+        }
+        match self {
+            InputFormat::Hex => HexString::parse_exact(s),
+            InputFormat::Decimal => address_from_decimal(s),
+            InputFormat::Auto if s.starts_with("0d") => address_from_decimal(s),
+            InputFormat::Auto if s.len() != 40 && s.bytes().all(|b| b.is_ascii_digit()) => {
+                address_from_decimal(s)
+            }
+            InputFormat::Auto => HexString::parse_exact(s),
+        }
+    }
+}
+
+/// The [`DerivationScheme`]s selectable with `--scheme`.
+#[derive(Copy, Clone, clap::ValueEnum)]
+enum Scheme {
+    /// The Sovereign SDK derivation
+    SovV1,
+    /// Like `sov-v1`, but without padding the token address to 32 bytes. For experimentation only
+    Unpadded,
+}
+
+impl Scheme {
+    fn get(self) -> &'static dyn DerivationScheme {
+        match self {
+            Scheme::SovV1 => &SovV1Scheme,
+            Scheme::Unpadded => &UnpaddedScheme,
+        }
+    }
+}
+
+#[derive(Copy, Clone, clap::ValueEnum)]
+enum OutputFormat {
+    /// Labeled, human-readable lines
+    Text,
+    /// A header row followed by one row per result
+    Csv,
+    /// A JSON object, or an array of objects for `--token-list`
+    Json,
+    /// The same structure as `json`, as YAML
+    Yaml,
+    /// One `INSERT INTO warp_routes` statement per result
+    Sql,
+    /// A GitHub-flavored Markdown table with one row per result
+    Markdown,
+}
+
+#[derive(Copy, Clone, clap::ValueEnum)]
+enum RowsFormat {
+    /// A header row followed by comma-separated rows. Fields may be quoted like the CSV output
+    Csv,
+    /// An array of objects
+    Json,
+}
+
+#[derive(Copy, Clone, clap::ValueEnum)]
+enum ConfigFormat {
+    /// A JSON object
+    Json,
+    /// A YAML mapping
+    Yaml,
+}
+
+impl ConfigFormat {
+    /// YAML for `.yaml` and `.yml` files, JSON for everything else.
+    fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("yaml" | "yml") => ConfigFormat::Yaml,
+            _ => ConfigFormat::Json,
+        }
+    }
+}
+
+/// Common Ethereum denominations, for display only.
+#[derive(Copy, Clone, clap::ValueEnum)]
+enum Unit {
+    /// 0 decimals
+    Wei,
+    /// 3 decimals
+    Kwei,
+    /// 6 decimals
+    Mwei,
+    /// 9 decimals
+    Gwei,
+    /// 12 decimals
+    Szabo,
+    /// 15 decimals
+    Finney,
+    /// 18 decimals
+    Ether,
+}
+
+impl Unit {
+    /// The number of decimals between this unit and wei.
+    fn decimals(self) -> u8 {
+        match self {
+            Unit::Wei => 0,
+            Unit::Kwei => 3,
+            Unit::Mwei => 6,
+            Unit::Gwei => 9,
+            Unit::Szabo => 12,
+            Unit::Finney => 15,
+            Unit::Ether => 18,
+        }
+    }
+}
+
+#[derive(clap::Subcommand)]
+enum Command {
+    /// Prints the SHA-256 hash of the given bytes
+    Hash {
+        /// The hex-encoded preimage. Read from stdin if omitted
+        preimage: Option<HexString>,
+    },
+    /// Computes the token ID directly from an existing warp route ID
+    TokenIdFromRoute {
+        /// The warp route ID, as hex or bech32
+        #[clap(long, value_parser = parse_hash_or_bech32)]
+        warp_route_id: HexHash,
+        /// The number of decimals of the synthetic token
+        #[clap(long, default_value_t = 18)]
+        decimals: u8,
+        /// Hash this token name verbatim instead of the conventional `Synthetic token for ...` name
+        #[clap(long)]
+        token_name: Option<String>,
+        #[command(flatten)]
+        decimals_check: DecimalsCheck,
+    },
+    /// Checks the derivation against the built-in known-answer vectors
+    Selftest,
+    /// Checks the derivation against a JSON array of reference vectors, e.g. exported from the
+    /// Sovereign SDK, stopping at the first mismatch
+    Conformance {
+        /// The vector file, or `-` for stdin. Each entry has `deployer`, `token_address`,
+        /// `decimals`, `warp_route_id` and `token_id` fields
+        file: PathBuf,
+    },
+    /// Prints every intermediate and final representation of a warp route, labeled
+    DeriveAll {
+        /// The address that will be used to deploy the warp route on the Sovereign SDK chain
+        #[clap(long, short)]
+        deployer: Address,
+        /// The ethereum address of the wrapped token on the EVM chain
+        #[clap(long, short)]
+        token_address: Address,
+        /// The number of decimals of the synthetic token
+        #[clap(long, default_value_t = 18)]
+        decimals: u8,
+        /// The token's Ethereum unit, printed with its number of decimals for reference. This
+        /// never changes the hashed `--decimals`; a mismatch only prints a warning
+        #[clap(long, value_enum)]
+        unit: Option<Unit>,
+        #[command(flatten)]
+        decimals_check: DecimalsCheck,
+        /// Also print the warp route ID as bech32m with this prefix. The Sovereign SDK doesn't
+        /// define one, so it's omitted by default
+        #[clap(long)]
+        warp_route_hrp: Option<String>,
+    },
+    /// Prints the token ID for every number of decimals in a range, for a single warp route
+    SweepDecimals {
+        /// The address that will be used to deploy the warp route on the Sovereign SDK chain
+        #[clap(long, short)]
+        deployer: Address,
+        /// The ethereum address of the wrapped token on the EVM chain
+        #[clap(long, short)]
+        token_address: Address,
+        /// The smallest number of decimals to try
+        #[clap(long, default_value_t = 0)]
+        from: u8,
+        /// The largest number of decimals to try, inclusive
+        #[clap(long, default_value_t = 18)]
+        to: u8,
+    },
+    /// Prints an order-independent ID for a set of token IDs: the SHA-256 of the sorted,
+    /// deduplicated IDs, as bech32m with the `group_` prefix
+    GroupId {
+        /// A token ID in the set, as hex or bech32. Repeat for each token
+        #[clap(long = "token-id", value_parser = parse_hash_or_bech32, required = true)]
+        token_ids: Vec<HexHash>,
+    },
+    /// Computes the token IDs of two `--batch` style files and prints, as CSV, every token
+    /// address that was added, removed or whose token ID changed
+    Compare {
+        /// The old `token_address[,decimals]` file, or `-` for stdin
+        old: PathBuf,
+        /// The new `token_address[,decimals]` file, or `-` for stdin
+        new: PathBuf,
+        /// The deployer of the old routes
+        #[clap(long, short)]
+        deployer: Address,
+        /// The deployer of the new routes, if it changed. Defaults to `--deployer`
+        #[clap(long)]
+        new_deployer: Option<Address>,
+    },
+    /// Computes the warp route of every row of a CSV or JSON file, each with its own deployer.
+    /// Rows that fail to parse are reported on stderr and skipped
+    DeriveBatch {
+        /// A CSV file with a header naming its columns, or a JSON array of objects, or `-` for
+        /// stdin. Each row has a `deployer`, a `token_address`, and optionally `decimals`
+        /// (defaulting to 18) and a `token_name`. Other columns are ignored
+        input: PathBuf,
+        /// How `input` is parsed. Defaults to `json` for `.json` files and `csv` otherwise
+        #[clap(long, value_enum)]
+        input_format: Option<RowsFormat>,
+        /// Write the results to this file instead of stdout
+        #[clap(long)]
+        output: Option<PathBuf>,
+        /// The format of the results
+        #[clap(long, value_enum, default_value_t = OutputFormat::Csv)]
+        format: OutputFormat,
+        /// Fail instead of overwriting `--output` if it already exists
+        #[clap(long, requires = "output")]
+        no_clobber: bool,
+        /// The bech32 prefix used for the token IDs
+        #[clap(long, default_value = "token_")]
+        hrp: String,
+        /// Abort without printing anything if the file has more than this many rows
+        #[clap(long)]
+        max_batch_size: Option<usize>,
+    },
+    /// Computes the warp route ID and token ID. This is the default without a subcommand, and
+    /// takes the same flags
+    Derive(Box<DeriveArgs>),
+    /// Prints what kind of identifier a value is, along with its decoded bytes
+    Decode {
+        /// An address, hash or `token_...` ID
+        value: String,
+        /// The comma-separated bech32 prefixes to accept
+        #[clap(long, value_delimiter = ',', value_parser = parse_hrp, default_value = "token_")]
+        hrp_allowlist: Vec<Hrp>,
+    },
+    /// Fails, explaining the difference, unless a warp route has the expected IDs
+    Verify {
+        /// The address that will be used to deploy the warp route on the Sovereign SDK chain
+        #[clap(long, short)]
+        deployer: Address,
+        /// The ethereum address of the wrapped token on the EVM chain
+        #[clap(long, short)]
+        token_address: Address,
+        /// The number of decimals of the synthetic token
+        #[clap(long, default_value_t = 18)]
+        decimals: u8,
+        /// The expected warp route ID, as hex or bech32
+        #[clap(long, value_parser = parse_hash_or_bech32, required_unless_present = "token_id")]
+        warp_route_id: Option<HexHash>,
+        /// The expected token ID, as hex or bech32
+        #[clap(long, value_parser = parse_hash_or_bech32)]
+        token_id: Option<HexHash>,
+        /// Hash this token name verbatim instead of the conventional `Synthetic token for ...` name
+        #[clap(long)]
+        token_name: Option<String>,
+    },
+    /// Prints each identifier in its canonical form: lowercase `0x` hex for addresses and hashes,
+    /// and a single lowercase bech32m string for token IDs
+    Normalize {
+        /// The addresses, hashes or token IDs to normalize. Read one per line from stdin if
+        /// omitted. Whitespace inside a value, e.g. from `--group-bech32`, is removed
+        values: Vec<String>,
+        /// Also remove this `--group-separator` from the values
+        #[clap(long, value_parser = parse_group_separator)]
+        group_separator: Option<char>,
+    },
+    /// Prints a roff man page for this CLI, e.g. `sov-warp-utils man > sov-warp-utils.1`
+    Man,
+    /// Serves `POST /compute`, which takes a JSON `{deployer, token_address, decimals}` object
+    /// and returns the derived warp route as JSON
+    #[cfg(feature = "server")]
+    Serve {
+        /// The port to listen on
+        #[clap(long, default_value_t = 8080)]
+        port: u16,
+        /// The address to listen on. Only local clients can connect by default; pass `0.0.0.0`
+        /// to accept requests from any host
+        #[clap(long, default_value_t = std::net::IpAddr::from([127, 0, 0, 1]))]
+        bind: std::net::IpAddr,
+    },
+}
+
+fn main() -> std::process::ExitCode {
+    match run() {
+        Ok(()) => std::process::ExitCode::SUCCESS,
+        // The reader closed stdout early, e.g. `| head`, so there's no one left to print to.
+        Err(e) if is_broken_pipe(&e) => std::process::ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {e:?}");
+            std::process::ExitCode::FAILURE
+        }
+    }
+}
+
+/// Whether `error` was caused by writing to a closed pipe.
+fn is_broken_pipe(error: &anyhow::Error) -> bool {
+    error.chain().any(|cause| {
+        cause
+            .downcast_ref::<std::io::Error>()
+            .is_some_and(|e| e.kind() == std::io::ErrorKind::BrokenPipe)
+    })
+}
+
+fn run() -> anyhow::Result<()> {
+    let Args { command, derive } = Args::parse();
+
+    match command {
+        Some(Command::Hash { preimage }) => return hash(preimage),
+        Some(Command::TokenIdFromRoute {
+            warp_route_id,
+            decimals,
+            token_name,
+            decimals_check,
+        }) => {
+            decimals_check.warn(decimals);
+            let token_id = match &token_name {
+                Some(token_name) => get_token_id_with_name(warp_route_id, token_name, decimals),
+                None => get_token_id(warp_route_id, decimals),
+            };
+            return write_stdout(&format!(
+                "Token ID: {}\n",
+                format_token_id(token_id, token_hrp())
+            ));
+        }
+        Some(Command::Selftest) => return selftest(),
+        Some(Command::Conformance { file }) => return conformance(&file),
+        Some(Command::DeriveAll {
+            deployer,
+            token_address,
+            decimals,
+            unit,
+            decimals_check,
+            warp_route_hrp,
+        }) => {
+            decimals_check.warn(decimals);
+            return derive_all(deployer, token_address, decimals, unit, warp_route_hrp);
+        }
+        Some(Command::GroupId { token_ids }) => {
+            let group_id = get_group_id(&token_ids);
+            return write_stdout(&format!(
+                "Group ID: {}\n",
+                format_token_id(group_id, group_hrp())
+            ));
+        }
+        Some(Command::Compare {
+            old,
+            new,
+            deployer,
+            new_deployer,
+        }) => return compare(&old, &new, deployer, new_deployer.unwrap_or(deployer)),
+        Some(Command::SweepDecimals {
+            deployer,
+            token_address,
+            from,
+            to,
+        }) => {
+            if from > to {
+                anyhow::bail!("--from {from} is greater than --to {to}");
+            }
+            let warp_route_id = get_warp_route_id(token_address, deployer);
+            let mut out = format!("Warp Route ID: {warp_route_id}\n");
+            for decimals in from..=to {
+                let token_id = get_token_id(warp_route_id, decimals);
+                writeln!(
+                    out,
+                    "Decimals {decimals}: {}",
+                    format_token_id(token_id, token_hrp())
+                )?;
+            }
+            return write_stdout(&out);
+        }
+        Some(Command::Normalize {
+            values,
+            group_separator,
+        }) => return normalize(values, group_separator),
+        Some(Command::Man) => return man(),
+        #[cfg(feature = "server")]
+        Some(Command::Serve { port, bind }) => return serve(bind, port),
+        Some(Command::DeriveBatch {
+            input,
+            input_format,
+            output,
+            format,
+            no_clobber,
+            hrp,
+            max_batch_size,
+        }) => {
+            let hrp = resolve_hrp(&normalize_hrp(hrp), None)?;
+            return derive_batch(
+                &input,
+                input_format,
+                output.as_deref(),
+                no_clobber,
+                format,
+                hrp,
+                max_batch_size,
+            );
+        }
+        Some(Command::Derive(args)) => return run_derive(*args),
+        Some(Command::Decode {
+            value,
+            hrp_allowlist,
+        }) => return inspect(&value, &hrp_allowlist),
+        Some(Command::Verify {
+            deployer,
+            token_address,
+            decimals,
+            warp_route_id,
+            token_id,
+            token_name,
+        }) => {
+            return verify(
+                deployer,
+                token_address,
+                decimals,
+                token_name,
+                warp_route_id,
+                token_id,
+            );
+        }
+        None => {}
+    }
+    run_derive(derive)
 }
 
-impl<T> serde::Serialize for HexString<T>
-where
-    T: AsRef<[u8]>,
-{
-    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
-    where
-        S: serde::Serializer,
+/// Runs the `derive` subcommand, which is also the default without a subcommand.
+fn run_derive(args: DeriveArgs) -> anyhow::Result<()> {
+    let DeriveArgs {
+        value,
+        config,
+        config_format,
+        deployer,
+        token_address,
+        from_receipt,
+        native,
+        route_type,
+        input_format,
+        token_list,
+        hyperlane_config,
+        batch,
+        skip,
+        take,
+        sort_output,
+        timings,
+        json_array,
+        fail_fast,
+        continue_on_error: _,
+        max_batch_size,
+        hrp,
+        hrp_allowlist,
+        remote_domain,
+        format,
+        output,
+        no_clobber,
+        quiet,
+        manifest,
+        update,
+        validate_only,
+        allow_same,
+        decimals_check,
+        local_decimals,
+        remote_decimals,
+        allow_trailing_zeros,
+        #[cfg(feature = "ens")]
+        resolve_ens,
+        token_name,
+        name_salt,
+        scheme,
+        print_config,
+        show_padded_token,
+        show_token_id_hex,
+        trim_leading_zeros,
+        show_decimals,
+        group_bech32,
+        group_separator,
+        bech32_data_only,
+        short,
+        assert_warp_route_id,
+        assert_token_id,
+        explain,
+        explain_json,
+        double_check,
+        hash_cmd,
+    } = args;
+    let allow_same = allow_same || quiet;
+    let allow_trailing_zeros = allow_trailing_zeros || quiet;
+    let route_type = if native {
+        Some(RouteType::Native)
+    } else {
+        route_type
+    };
+    let native = match route_type {
+        Some(RouteType::Native) if token_address.is_some() => {
+            anyhow::bail!("--route-type native hashes no token address, so drop --token-address")
+        }
+        Some(RouteType::Native) => true,
+        Some(RouteType::Collateral) | None => false,
+    };
+    let decimals_check = DecimalsCheck {
+        allow_large_decimals: decimals_check.allow_large_decimals || quiet,
+        ..decimals_check
+    };
+    if print_config {
+        let config = DerivationConfig::new(scheme, normalize_hrp(hrp), token_name, name_salt);
+        return write_stdout(&(serde_json::to_string_pretty(&config)? + "\n"));
+    }
+    if let Some(value) = value {
+        return inspect(&value, &hrp_allowlist);
+    }
+    if let Some(path) = update {
+        return update_manifest(&path, scheme.get());
+    }
+    let mut merged = Config::default();
+    for path in &config {
+        merged = read_config(path, config_format, input_format)?.or(merged);
+    }
+    let config = merged;
+    let deployer = deployer
+        .or(config.deployer)
+        .ok_or_else(|| anyhow::anyhow!("--deployer is required unless it is set in --config"))?;
+    let deployer = input_format
+        .parse_address(&deployer)
+        .map_err(|e| anyhow::anyhow!("Invalid --deployer {deployer}, error: {e}"))?;
+    if !allow_trailing_zeros {
+        warn_if_reversed("deployer", deployer);
+    }
+    let hrp = if quiet {
+        hrp.to_ascii_lowercase()
+    } else {
+        normalize_hrp(hrp)
+    };
+    if remote_domain.len() > 1 && !hrp.contains("{domain}") {
+        anyhow::bail!(
+            "Warp route and token IDs don't depend on the remote domain, so multiple --remote-domain values require a {{domain}} placeholder in --hrp"
+        );
+    }
+    let domains: Vec<_> = if remote_domain.is_empty() {
+        vec![None]
+    } else {
+        remote_domain.into_iter().map(Some).collect()
+    };
+    let hrps = domains
+        .iter()
+        .map(|&domain| Ok((domain, resolve_hrp(&hrp, domain)?)))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    let display = DisplayOptions {
+        trim_leading_zeros,
+        show_decimals,
+        group_bech32: group_bech32.then_some(group_separator.unwrap_or(' ')),
+        bech32_data_only,
+        short,
+        quiet,
+        show_token_id_hex,
+    };
+    let deriver = Deriver {
+        deployer,
+        hrps,
+        scheme: scheme.get(),
+        token_name,
+        name_salt,
+        show_padded_token,
+        double_check,
+        hash_cmd,
+    };
+    if let Some(path) = batch {
+        let format = if json_array {
+            OutputFormat::Json
+        } else {
+            format.unwrap_or(OutputFormat::Csv)
+        };
+        let entries = Batch {
+            path: &path,
+            input_format,
+            skip,
+            take,
+            max_batch_size,
+            fail_fast,
+        };
+        let output = BatchOutput {
+            format,
+            json_array,
+            display,
+            manifest: manifest.as_deref(),
+            sort_output,
+            timings,
+        };
+        return run_batch(
+            entries,
+            &deriver,
+            output,
+            allow_same,
+            allow_trailing_zeros,
+            decimals_check,
+            validate_only,
+        );
+    }
+    let is_list = token_list.is_some() || hyperlane_config.is_some() || deriver.hrps.len() > 1;
+    let inputs = match (token_list, hyperlane_config, from_receipt) {
+        (Some(path), _, _) => {
+            let tokens = read_token_list(&path)?.tokens;
+            check_batch_size(tokens.len(), max_batch_size)?;
+            tokens
+                .into_iter()
+                .map(|token| (token.address, token.decimals))
+                .collect()
+        }
+        (None, Some(path), _) => {
+            let inputs = read_hyperlane_config(&path, quiet)?;
+            check_batch_size(inputs.len(), max_batch_size)?;
+            inputs
+        }
+        (None, None, Some(path)) => vec![(read_receipt_address(&path)?, local_decimals)],
+        (None, None, None) if native => vec![(NATIVE_TOKEN_ADDRESS, local_decimals)],
+        (None, None, None) => {
+            let token_address = token_address.or(config.token_address).ok_or_else(|| {
+                anyhow::anyhow!("--token-address is required unless it is set in --config")
+            })?;
+            #[cfg(feature = "ens")]
+            let resolved = match &resolve_ens {
+                Some(rpc) if token_address.contains('.') => {
+                    let resolved = resolve_ens_name(rpc, &token_address)?;
+                    if !quiet {
+                        eprintln!("Resolved {token_address} to {resolved}");
+                    }
+                    Some(resolved)
+                }
+                _ => None,
+            };
+            #[cfg(not(feature = "ens"))]
+            let resolved = None;
+            let token_address = match resolved {
+                Some(resolved) => resolved,
+                None => input_format.parse_address(&token_address).map_err(|e| {
+                    anyhow::anyhow!("Invalid --token-address {token_address}, error: {e}")
+                })?,
+            };
+            vec![(token_address, local_decimals)]
+        }
+    };
+    if let Some(remote_decimals) = remote_decimals
+        && remote_decimals > local_decimals
+        && !quiet
     {
-        use serde::ser::SerializeSeq;
-        if serializer.is_human_readable() {
-            serializer.serialize_str(&self.to_string())
+        eprintln!(
+            "Warning: --remote-decimals {remote_decimals} is more than --local-decimals {local_decimals}, so transfers to the Sovereign SDK chain lose the lowest {} digits",
+            remote_decimals - local_decimals
+        );
+    }
+    for &(token_address, decimals) in &inputs {
+        if !allow_same {
+            warn_if_same(deployer, token_address);
+        }
+        if !allow_trailing_zeros {
+            warn_if_reversed("token address", token_address);
+        }
+        decimals_check.warn(decimals);
+    }
+    if validate_only {
+        return Ok(());
+    }
+
+    let mut results = Vec::new();
+    for (i, _) in deriver.hrps.iter().enumerate() {
+        for &(token_address, decimals) in &inputs {
+            let row = deriver.row(i, token_address, decimals)?;
+            deriver.double_check(&row)?;
+            results.push(row);
+        }
+    }
+    if sort_output {
+        results.sort_by_key(|row| row.result.token_address);
+    }
+    // Only a `--token-list` can be empty, and it conflicts with the assertions.
+    if let Some(first) = results.first() {
+        check_assertions(first, assert_warp_route_id, assert_token_id)?;
+    }
+    if explain || explain_json {
+        let (token_address, decimals) = inputs[0];
+        let steps = derivation_steps(deployer, token_address, decimals);
+        let mut out = String::new();
+        if explain_json {
+            writeln!(out, "{}", serde_json::to_string_pretty(&steps)?)?;
         } else {
-            let inner_ref = self.0.as_ref();
-            let mut seq = serializer.serialize_seq(Some(inner_ref.len()))?;
-            for element in inner_ref {
-                seq.serialize_element(element)?;
+            for (i, step) in steps.iter().enumerate() {
+                writeln!(out, "{}. {}: {}", i + 1, step.step, step.description)?;
+                writeln!(out, "   {}", step.bytes)?;
             }
-            seq.end()
         }
+        return write_stdout(&out);
     }
+    let format = format.unwrap_or(if is_list {
+        OutputFormat::Json
+    } else {
+        OutputFormat::Text
+    });
+    let render = |format| render(&results, format, is_list, display);
+    match output {
+        Some(path) => {
+            write_output(&path, &render(format)?, no_clobber).map_err(|e| {
+                anyhow::anyhow!("Failed to write output {}, error: {}", path.display(), e)
+            })?;
+            if !quiet {
+                write_stdout(&render(OutputFormat::Text)?)?;
+            }
+        }
+        None => write_stdout(&render(format)?)?,
+    }
+    if let Some(path) = manifest {
+        write_manifest(&path, results)?;
+    }
+    Ok(())
 }
 
-impl<'de, T> serde::Deserialize<'de> for HexString<T>
-where
-    T: TryFrom<Vec<u8>> + AsRef<[u8]>,
-{
-    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
-    where
-        D: serde::Deserializer<'de>,
-    {
-        let bytes = if deserializer.is_human_readable() {
-            let string: String = serde::Deserialize::deserialize(deserializer)?;
-            parse_vec_u8(&string).map_err(serde::de::Error::custom)?
+/// Hashes `preimage` with `--hash-cmd`: runs `cmd` with the shell, writes the preimage to its stdin
+/// and parses the first word of its stdout as a hex digest. This matches `sha256sum`'s output.
+fn run_hash_cmd(cmd: &str, preimage: &[u8]) -> anyhow::Result<[u8; 32]> {
+    use std::io::Write as _;
+    use std::process::{Command, Stdio};
+
+    #[cfg(windows)]
+    let mut command = Command::new("cmd");
+    #[cfg(windows)]
+    command.arg("/C");
+    #[cfg(not(windows))]
+    let mut command = Command::new("sh");
+    #[cfg(not(windows))]
+    command.arg("-c");
+    let mut child = command
+        .arg(cmd)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .map_err(|e| anyhow::anyhow!("Failed to run --hash-cmd {}, error: {}", cmd, e))?;
+    // Preimages are far smaller than a pipe buffer, so this can't block on the child's stdout.
+    child
+        .stdin
+        .take()
+        .expect("stdin is piped")
+        .write_all(preimage)
+        .map_err(|e| {
+            anyhow::anyhow!(
+                "Failed to write the preimage to --hash-cmd {}, error: {}",
+                cmd,
+                e
+            )
+        })?;
+    let output = child
+        .wait_with_output()
+        .map_err(|e| anyhow::anyhow!("Failed to run --hash-cmd {}, error: {}", cmd, e))?;
+    if !output.status.success() {
+        anyhow::bail!("--hash-cmd {cmd} failed with {}", output.status);
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let digest = stdout.split_whitespace().next().unwrap_or_default();
+    HexString::parse_exact::<32>(digest)
+        .map(|digest| digest.0)
+        .map_err(|e| {
+            anyhow::anyhow!(
+                "--hash-cmd {} printed {:?}, not a 32-byte hex digest, error: {}",
+                cmd,
+                digest,
+                e
+            )
+        })
+}
+
+/// Checks the warp route of `token_address` against the expected IDs, like `--assert-warp-route-id`
+/// and `--assert-token-id`.
+fn verify(
+    deployer: Address,
+    token_address: Address,
+    decimals: u8,
+    token_name: Option<String>,
+    expected_warp_route_id: Option<HexHash>,
+    expected_token_id: Option<HexHash>,
+) -> anyhow::Result<()> {
+    let (result, token_id_hex) = compute_with_scheme(
+        &SovV1Scheme,
+        deployer,
+        token_address,
+        decimals,
+        token_name.as_deref(),
+        token_hrp(),
+    );
+    let row = Row {
+        remote_domain: None,
+        result,
+        padded_token_address: None,
+        token_id_hex,
+    };
+    check_assertions(&row, expected_warp_route_id, expected_token_id)?;
+    write_stdout("OK: the expected IDs match\n")
+}
+
+/// Prints `s` to stdout, returning an error instead of panicking if stdout is a closed pipe.
+fn write_stdout(s: &str) -> anyhow::Result<()> {
+    use std::io::Write as _;
+    let mut stdout = std::io::stdout().lock();
+    stdout.write_all(s.as_bytes())?;
+    stdout.flush()?;
+    Ok(())
+}
+
+/// The parameters that determine the derived IDs, as printed by `--print-config`.
+#[derive(serde::Serialize)]
+struct DerivationConfig {
+    tool: &'static str,
+    version: &'static str,
+    scheme: String,
+    hash: &'static str,
+    warp_route_id_preimage: &'static str,
+    token_address_bytes: usize,
+    separator: HexString<[u8; 1]>,
+    deployer_bytes: usize,
+    remote_domain_hashed: bool,
+    token_id_preimage: &'static str,
+    token_name: String,
+    token_id_last_byte: &'static str,
+    hrp: String,
+}
+
+impl DerivationConfig {
+    fn new(
+        scheme: Scheme,
+        hrp: String,
+        token_name: Option<String>,
+        name_salt: Option<String>,
+    ) -> Self {
+        let token_name =
+            token_name.unwrap_or_else(|| format!("{TOKEN_NAME_PREFIX}{{warp_route_id}}"));
+        DerivationConfig {
+            tool: env!("CARGO_PKG_NAME"),
+            version: env!("CARGO_PKG_VERSION"),
+            scheme: scheme
+                .to_possible_value()
+                .expect("no schemes are skipped")
+                .get_name()
+                .to_string(),
+            hash: "sha256",
+            warp_route_id_preimage: "token_address || separator || deployer",
+            token_address_bytes: match scheme {
+                Scheme::SovV1 => 32,
+                Scheme::Unpadded => 20,
+            },
+            separator: HexString([SEPARATOR_BYTE]),
+            deployer_bytes: 20,
+            remote_domain_hashed: false,
+            token_id_preimage: "warp_route_id || token_name || decimals",
+            token_name: token_name + name_salt.as_deref().unwrap_or(""),
+            token_id_last_byte: "decimals",
+            hrp,
+        }
+    }
+}
+
+/// The inputs shared by every warp route computed in one run.
+struct Deriver {
+    deployer: Address,
+    /// The token ID prefix for each `--remote-domain`, or a single unlabeled prefix
+    hrps: Vec<(Option<u32>, Hrp)>,
+    scheme: &'static dyn DerivationScheme,
+    token_name: Option<String>,
+    name_salt: Option<String>,
+    show_padded_token: bool,
+    double_check: bool,
+    /// Hash with this external command instead of the built-in SHA-256
+    hash_cmd: Option<String>,
+}
+
+impl Deriver {
+    /// Computes the warp route of `token_address` for the `i`th entry of `hrps`.
+    fn row(&self, i: usize, token_address: Address, decimals: u8) -> anyhow::Result<Row> {
+        let (remote_domain, hrp) = self.hrps[i];
+        let (result, token_id_hex) = match &self.hash_cmd {
+            Some(cmd) => {
+                let (warp_route_id, token_name, token_id) = derive_with_hash_fn(
+                    self.deployer,
+                    token_address,
+                    decimals,
+                    |warp_route_id| {
+                        let base = self
+                            .token_name
+                            .clone()
+                            .unwrap_or_else(|| get_token_name(warp_route_id));
+                        base + self.name_salt.as_deref().unwrap_or("")
+                    },
+                    |preimage| run_hash_cmd(cmd, preimage),
+                )?;
+                let result = WarpRouteResult {
+                    deployer: self.deployer,
+                    token_address,
+                    decimals,
+                    warp_route_id,
+                    token_name,
+                    token_id: format_token_id(token_id, hrp),
+                };
+                (result, token_id)
+            }
+            None => {
+                let token_name = match &self.name_salt {
+                    Some(salt) => {
+                        let base = self.token_name.clone().unwrap_or_else(|| {
+                            let warp_route_id =
+                                self.scheme.warp_route_id(token_address, self.deployer);
+                            self.scheme.token_name(warp_route_id)
+                        });
+                        Some(base + salt)
+                    }
+                    None => self.token_name.clone(),
+                };
+                compute_with_scheme(
+                    self.scheme,
+                    self.deployer,
+                    token_address,
+                    decimals,
+                    token_name.as_deref(),
+                    hrp,
+                )
+            }
+        };
+        Ok(Row {
+            remote_domain,
+            result,
+            padded_token_address: self.show_padded_token.then(|| HexHash::from(token_address)),
+            token_id_hex,
+        })
+    }
+
+    /// With `--double-check`, recomputes `row` with [`derive_reference`] and fails if the results
+    /// differ.
+    fn double_check(&self, row: &Row) -> anyhow::Result<()> {
+        if !self.double_check {
+            return Ok(());
+        }
+        let result = &row.result;
+        let (warp_route_id, token_id) = derive_reference(
+            result.deployer,
+            result.token_address,
+            result.decimals,
+            &result.token_name,
+        );
+        let (_, computed_token_id) = bech32::decode(&result.token_id)?;
+        if warp_route_id != result.warp_route_id || token_id.0[..] != computed_token_id[..] {
+            anyhow::bail!(
+                "Double check failed for token address {}: computed warp route ID {} and token ID {}, but the reference implementation gives {} and {}",
+                result.token_address,
+                result.warp_route_id,
+                HexString(computed_token_id),
+                warp_route_id,
+                token_id
+            );
+        }
+        Ok(())
+    }
+
+    /// Computes the warp route of `token_address` for every entry of `hrps`.
+    fn rows(&self, token_address: Address, decimals: u8) -> anyhow::Result<Vec<Row>> {
+        (0..self.hrps.len())
+            .map(|i| self.row(i, token_address, decimals))
+            .collect()
+    }
+}
+
+fn warn_if_same(deployer: Address, token_address: Address) {
+    if token_address == deployer {
+        eprintln!(
+            "Warning: the deployer and token address are both {deployer}. Pass --allow-same if this is intended"
+        );
+    }
+}
+
+/// Warns if `address` ends in 4 or more zero bytes. That's vanishingly rare for a real address,
+/// but it's what a small value serialized in little-endian order looks like. Leading zeros aren't
+/// checked, since vanity and precompile addresses commonly have them.
+fn warn_if_reversed(label: &str, address: Address) {
+    let trailing_zeros = address.0.iter().rev().take_while(|&&b| b == 0).count();
+    if trailing_zeros >= 4 && trailing_zeros < address.0.len() {
+        eprintln!(
+            "Warning: the {label} {address} ends in {trailing_zeros} zero bytes, so its bytes may be reversed. Pass --allow-trailing-zeros if this is intended"
+        );
+    }
+}
+
+/// The soft check that decimals are in a plausible range for a real token.
+#[derive(Copy, Clone, clap::Args)]
+struct DecimalsCheck {
+    /// Warn when a token has more than this many decimals, which likely indicates a typo
+    #[clap(long, default_value_t = 36)]
+    max_decimals: u8,
+    /// Don't warn about decimals above `--max-decimals`
+    #[clap(long)]
+    allow_large_decimals: bool,
+}
+
+impl DecimalsCheck {
+    /// Warns if `decimals` exceeds `--max-decimals`, unless `--allow-large-decimals` is set. The
+    /// IDs are still computed either way.
+    fn warn(self, decimals: u8) {
+        if !self.allow_large_decimals && decimals > self.max_decimals {
+            eprintln!(
+                "Warning: {decimals} decimals is more than --max-decimals {}, which is unusual for a real token. Pass --allow-large-decimals if this is intended",
+                self.max_decimals
+            );
+        }
+    }
+}
+
+/// The `(line number, line)` of each `--batch` entry to compute.
+type BatchEntries = Box<dyn Iterator<Item = anyhow::Result<(usize, String)>>>;
+
+/// The `--batch` input, the slice of it to compute and how to handle lines that fail to parse.
+struct Batch<'a> {
+    path: &'a Path,
+    input_format: InputFormat,
+    skip: usize,
+    take: Option<usize>,
+    max_batch_size: Option<usize>,
+    /// Stop at the first line that fails to parse instead of skipping it
+    fail_fast: bool,
+}
+
+impl Batch<'_> {
+    /// Returns the `(line number, line)` of every entry in the selected slice, reading lazily
+    /// unless `max_batch_size` requires checking the slice's length up front.
+    fn entries(&self) -> anyhow::Result<BatchEntries> {
+        use std::io::BufRead;
+
+        let reader: Box<dyn std::io::BufRead> = if self.path == Path::new("-") {
+            Box::new(std::io::stdin().lock())
         } else {
-            serde::Deserialize::deserialize(deserializer)?
+            let file = std::fs::File::open(self.path).map_err(|e| {
+                anyhow::anyhow!("Failed to open batch {}, error: {}", self.path.display(), e)
+            })?;
+            Box::new(std::io::BufReader::new(file))
+        };
+        let path = self.path.to_path_buf();
+        let entries = reader
+            .lines()
+            .enumerate()
+            .map(move |(i, line)| {
+                let line = line.map_err(|e| {
+                    anyhow::anyhow!("Failed to read batch {}, error: {}", path.display(), e)
+                })?;
+                Ok((i + 1, line.trim().to_string()))
+            })
+            .filter(
+                |entry| !matches!(entry, Ok((_, line)) if line.is_empty() || line.starts_with('#')),
+            )
+            .skip(self.skip)
+            .take(self.take.unwrap_or(usize::MAX));
+        match self.max_batch_size {
+            Some(max) => {
+                let entries: Vec<_> = entries.take(max.saturating_add(1)).collect();
+                check_batch_size(entries.len(), Some(max))?;
+                Ok(Box::new(entries.into_iter()))
+            }
+            None => Ok(Box::new(entries)),
+        }
+    }
+
+    /// Parses a `token_address[,decimals]` line.
+    fn parse(&self, line: &str) -> anyhow::Result<(Address, u8)> {
+        let (token_address, decimals) = match line.split_once(',') {
+            Some((token_address, decimals)) => {
+                let decimals = decimals.trim().parse().map_err(|e| {
+                    anyhow::anyhow!("Invalid decimals {}, error: {}", decimals.trim(), e)
+                })?;
+                (token_address.trim(), decimals)
+            }
+            None => (line, 18),
         };
+        let token_address = self
+            .input_format
+            .parse_address(token_address)
+            .map_err(|e| anyhow::anyhow!("Invalid token address {token_address}, error: {e}"))?;
+        Ok((token_address, decimals))
+    }
+}
+
+/// How [`run_batch`] prints its results.
+struct BatchOutput<'a> {
+    format: OutputFormat,
+    /// Wrap JSON results in a single array instead of printing one per line
+    json_array: bool,
+    display: DisplayOptions,
+    /// Also collect every result into a `--manifest` written at the end
+    manifest: Option<&'a Path>,
+    /// Buffer the results and print them sorted by token address at the end
+    sort_output: bool,
+    /// Report the time spent in each stage at the end
+    timings: bool,
+}
+
+/// The time a batch spent in each stage, as reported by `--timings`.
+#[derive(Default)]
+struct Timings {
+    parse: Duration,
+    /// Hashing and bech32 encoding the IDs
+    derive: Duration,
+    /// Formatting and writing the results, including sorting them
+    output: Duration,
+}
+
+impl Timings {
+    /// Prints each stage's total and its average over the `parsed` lines or `derived` entries.
+    fn report(&self, parsed: usize, derived: usize) {
+        for (stage, total, count) in [
+            ("Parsing", self.parse, parsed),
+            ("Deriving", self.derive, derived),
+            ("Printing", self.output, derived),
+        ] {
+            let average = total.div_f64(count.max(1) as f64);
+            eprintln!("{stage}: {total:?} total, {average:?} per entry");
+        }
+    }
+}
+
+/// A deployment manifest: every computed result, plus when and by what version it was computed.
+#[derive(serde::Serialize, serde::Deserialize)]
+struct Manifest<E = Row> {
+    tool: String,
+    version: String,
+    /// Seconds since the Unix epoch
+    generated_at: u64,
+    entries: Vec<E>,
+}
+
+/// The fields of a manifest entry that `--update` recomputes from, along with the stored IDs to
+/// compare against. Other fields, like `remote_domain`, are kept as they are.
+#[derive(serde::Deserialize)]
+struct ManifestEntry {
+    deployer: Address,
+    token_address: Address,
+    decimals: u8,
+    warp_route_id: HexHash,
+    token_name: String,
+    token_id: String,
+}
+
+/// Writes `entries` to the `--manifest` at `path`, as YAML or JSON depending on its extension.
+fn write_manifest<E: serde::Serialize>(path: &Path, entries: Vec<E>) -> anyhow::Result<()> {
+    let generated_at = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)?
+        .as_secs();
+    let manifest = Manifest {
+        tool: env!("CARGO_PKG_NAME").to_string(),
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        generated_at,
+        entries,
+    };
+    let contents = match ConfigFormat::from_path(path) {
+        ConfigFormat::Json => serde_json::to_string_pretty(&manifest)? + "\n",
+        ConfigFormat::Yaml => serde_yaml::to_string(&manifest)?,
+    };
+    std::fs::write(path, contents)
+        .map_err(|e| anyhow::anyhow!("Failed to write manifest {}, error: {}", path.display(), e))
+}
 
-        Ok(HexString(bytes.try_into().map_err(|_| {
-            serde::de::Error::custom("Invalid hex string length")
-        })?))
+/// Recomputes every entry of the manifest at `path` with `scheme`, replacing the IDs of entries
+/// whose stored IDs don't match, and writes the manifest back if any did. A token name that was
+/// the conventional name for the stored warp route is recomputed along with it; any other token
+/// name is kept and hashed as is. The token ID keeps its stored prefix.
+fn update_manifest(path: &Path, scheme: &dyn DerivationScheme) -> anyhow::Result<()> {
+    let contents = read_input(path)?;
+    let manifest: Manifest<serde_json::Value> = match ConfigFormat::from_path(path) {
+        ConfigFormat::Json => serde_json::from_str(&contents).map_err(anyhow::Error::from),
+        ConfigFormat::Yaml => serde_yaml::from_str(&contents).map_err(anyhow::Error::from),
     }
+    .map_err(|e| anyhow::anyhow!("Failed to parse manifest {}, error: {}", path.display(), e))?;
+    let total = manifest.entries.len();
+    let mut entries = Vec::with_capacity(total);
+    let mut updated = 0;
+    for (i, mut value) in manifest.entries.into_iter().enumerate() {
+        let entry: ManifestEntry = serde_json::from_value(value.clone())
+            .map_err(|e| anyhow::anyhow!("Invalid manifest entry {i}, error: {e}"))?;
+        let (hrp, _) = bech32::decode(&entry.token_id).map_err(|e| {
+            anyhow::anyhow!(
+                "Invalid token ID {} in entry {i}, error: {e}",
+                entry.token_id
+            )
+        })?;
+        let token_name = (entry.token_name != scheme.token_name(entry.warp_route_id))
+            .then_some(entry.token_name.as_str());
+        let (result, token_id_hex) = compute_with_scheme(
+            scheme,
+            entry.deployer,
+            entry.token_address,
+            entry.decimals,
+            token_name,
+            hrp,
+        );
+        if result.warp_route_id != entry.warp_route_id
+            || result.token_name != entry.token_name
+            || result.token_id != entry.token_id
+        {
+            updated += 1;
+            eprintln!(
+                "Updated entry {i}: {} -> {}",
+                entry.token_id, result.token_id
+            );
+            value["warp_route_id"] = result.warp_route_id.to_string().into();
+            value["token_name"] = result.token_name.into();
+            if value.get("token_id_hex").is_some() {
+                value["token_id_hex"] = token_id_hex.to_string().into();
+            }
+            value["token_id"] = result.token_id.into();
+            if value.get("padded_token_address").is_some() {
+                value["padded_token_address"] =
+                    HexHash::from(entry.token_address).to_string().into();
+            }
+        }
+        entries.push(value);
+    }
+    eprintln!("Updated {updated} of {total} entries");
+    if updated > 0 {
+        write_manifest(path, entries)?;
+    }
+    Ok(())
 }
 
-impl<T: BorshSerialize + AsRef<[u8]>> BorshSerialize for HexString<T> {
-    fn serialize<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
-        self.0.serialize(writer)
+/// Writes `contents` to `path`, failing if it already exists when `no_clobber` is set.
+fn write_output(path: &Path, contents: &str, no_clobber: bool) -> std::io::Result<()> {
+    use std::io::Write as _;
+
+    if !no_clobber {
+        return std::fs::write(path, contents);
     }
+    std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(path)?
+        .write_all(contents.as_bytes())
 }
 
-impl<T: BorshDeserialize + AsRef<[u8]>> BorshDeserialize for HexString<T> {
-    fn deserialize_reader<R: std::io::Read>(reader: &mut R) -> std::io::Result<Self> {
-        T::deserialize_reader(reader).map(Self)
+/// Compares `row` against the `--assert-*` values. On a mismatch, prints the expected and
+/// computed IDs to stderr along with the input that most likely differs, and fails.
+fn check_assertions(
+    row: &Row,
+    expected_warp_route_id: Option<HexHash>,
+    expected_token_id: Option<HexHash>,
+) -> anyhow::Result<()> {
+    let result = &row.result;
+    let route_matches = expected_warp_route_id.is_none_or(|id| id == result.warp_route_id);
+    let (hrp, token_id) = bech32::decode(&result.token_id)?;
+    let token_id = HexString(token_id).try_into_fixed::<32>()?;
+    let token_matches = expected_token_id.is_none_or(|id| id == token_id);
+    if route_matches && token_matches {
+        return Ok(());
+    }
+    if let Some(expected) = expected_warp_route_id {
+        eprintln!("Expected Warp Route ID: {expected}");
+        eprintln!("Computed Warp Route ID: {}", result.warp_route_id);
     }
+    if let Some(expected) = expected_token_id {
+        let expected_bech32 = format_token_id(expected, hrp);
+        eprintln!("Expected Token ID: {expected_bech32} ({expected})");
+        eprintln!("Computed Token ID: {} ({token_id})", result.token_id);
+    }
+    if !route_matches {
+        eprintln!(
+            "The warp route IDs differ, so the deployer or token address (or the derivation scheme) likely differs"
+        );
+    } else if let Some(expected) = expected_token_id {
+        // The final byte of a token ID is its decimals.
+        let expected_decimals = expected.0[31];
+        if expected_decimals != result.decimals {
+            eprintln!(
+                "The expected token ID ends in {expected_decimals} but {} decimals were used, so the decimals likely differ",
+                result.decimals
+            );
+        } else if expected_warp_route_id.is_some() {
+            eprintln!(
+                "The warp route IDs match but the token IDs differ, so the token name likely differs"
+            );
+        } else {
+            eprintln!(
+                "The token IDs differ but the decimals match. Pass --assert-warp-route-id to check whether the deployer or token address differs"
+            );
+        }
+    }
+    anyhow::bail!("The computed IDs don't match the expected ones")
 }
 
-impl<T: TryFrom<Vec<u8>> + AsRef<[u8]>> FromStr for HexString<T> {
-    type Err = anyhow::Error;
+/// Computes and prints the warp route of every entry in `batch` as it is read. Lines that fail to
+/// parse are reported on stderr and skipped, and a summary of the processed slice is printed to
+/// stderr at the end. Fails if any line failed, without writing the manifest.
+fn run_batch(
+    batch: Batch,
+    deriver: &Deriver,
+    output: BatchOutput,
+    allow_same: bool,
+    allow_trailing_zeros: bool,
+    decimals_check: DecimalsCheck,
+    validate_only: bool,
+) -> anyhow::Result<()> {
+    use std::io::Write as _;
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let bytes = parse_vec_u8(s)?;
-        Ok(HexString(bytes.try_into().map_err(|_| {
-            anyhow::anyhow!("Invalid hex string length")
-        })?))
+    let BatchOutput {
+        format,
+        json_array,
+        display,
+        manifest,
+        sort_output,
+        timings: report_timings,
+    } = output;
+    let mut timings = Timings::default();
+    let mut manifest_entries = Vec::new();
+    let entries = batch.entries()?;
+    let mut stdout = std::io::stdout().lock();
+    let has_domains = deriver.hrps.iter().any(|(domain, _)| domain.is_some());
+    if !validate_only {
+        match format {
+            OutputFormat::Csv => writeln!(
+                stdout,
+                "{}",
+                csv_header(
+                    has_domains,
+                    deriver.show_padded_token,
+                    display.show_token_id_hex
+                )
+            )?,
+            OutputFormat::Markdown => write!(stdout, "{}", markdown_header(has_domains))?,
+            _ => {}
+        }
+    }
+    let json_array = json_array && !validate_only;
+    if json_array {
+        write!(stdout, "[")?;
     }
+    let mut written = 0;
+    let mut emit = |row: Row| -> anyhow::Result<()> {
+        let mut out = String::new();
+        match format {
+            OutputFormat::Text => {
+                if !display.quiet && written > 0 {
+                    out.push('\n');
+                }
+                write_text(&mut out, &row, true, display)?;
+            }
+            OutputFormat::Csv => writeln!(out, "{}", csv_line(&row, display))?,
+            OutputFormat::Sql => writeln!(out, "{}", sql_insert(&row.result, display))?,
+            OutputFormat::Markdown => writeln!(out, "{}", markdown_row(&row, display))?,
+            OutputFormat::Json if json_array => {
+                let separator = if written == 0 { "\n  " } else { ",\n  " };
+                write!(
+                    out,
+                    "{separator}{}",
+                    serde_json::to_string(&row_value(&row, display)?)?
+                )?;
+            }
+            OutputFormat::Json => writeln!(
+                out,
+                "{}",
+                serde_json::to_string(&row_value(&row, display)?)?
+            )?,
+            OutputFormat::Yaml => write!(
+                out,
+                "---\n{}",
+                serde_yaml::to_string(&row_value(&row, display)?)?
+            )?,
+        }
+        written += 1;
+        stdout.write_all(out.as_bytes())?;
+        if manifest.is_some() {
+            manifest_entries.push(row);
+        }
+        Ok(())
+    };
+    let (mut processed, mut failed) = (0, 0);
+    let mut stopped_at = None;
+    let mut sorted = Vec::new();
+    for entry in entries {
+        let (line_number, line) = entry?;
+        let started = Instant::now();
+        let parsed = batch.parse(&line);
+        timings.parse += started.elapsed();
+        let (token_address, decimals) = match parsed {
+            Ok(entry) => entry,
+            Err(e) => {
+                failed += 1;
+                eprintln!("Line {line_number}: {e}");
+                if batch.fail_fast {
+                    stopped_at = Some(line_number);
+                    break;
+                }
+                continue;
+            }
+        };
+        processed += 1;
+        if !allow_same {
+            warn_if_same(deriver.deployer, token_address);
+        }
+        if !allow_trailing_zeros {
+            warn_if_reversed("token address", token_address);
+        }
+        decimals_check.warn(decimals);
+        if validate_only {
+            continue;
+        }
+        let started = Instant::now();
+        let rows = deriver.rows(token_address, decimals)?;
+        for row in &rows {
+            deriver.double_check(row)?;
+        }
+        timings.derive += started.elapsed();
+        let started = Instant::now();
+        for row in rows {
+            if sort_output {
+                sorted.push(row);
+            } else {
+                emit(row)?;
+            }
+        }
+        timings.output += started.elapsed();
+    }
+    let started = Instant::now();
+    // A stable sort, so rows for the same token keep their `--remote-domain` order.
+    sorted.sort_by_key(|row| row.result.token_address);
+    for row in sorted {
+        emit(row)?;
+    }
+    timings.output += started.elapsed();
+    if json_array {
+        writeln!(stdout, "\n]")?;
+    }
+    if !display.quiet {
+        eprintln!("Computed {processed} entries, {failed} failed");
+    }
+    if report_timings {
+        timings.report(processed + failed, processed);
+    }
+    if let Some(line_number) = stopped_at {
+        anyhow::bail!(
+            "Stopped at line {line_number} of batch {} because of --fail-fast",
+            batch.path.display()
+        );
+    }
+    if failed > 0 {
+        anyhow::bail!(
+            "Failed to parse {failed} of {} batch entries",
+            processed + failed
+        );
+    }
+    if let Some(path) = manifest {
+        write_manifest(path, manifest_entries)?;
+    }
+    Ok(())
 }
 
-impl<T> Display for HexString<T>
-where
-    T: AsRef<[u8]>,
-{
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "0x{}", hex::encode(&self.0))
+/// Display-only options that never affect the computed IDs.
+#[derive(Copy, Clone)]
+struct DisplayOptions {
+    trim_leading_zeros: bool,
+    show_decimals: bool,
+    /// The separator to group token IDs in `text` output with, if any
+    group_bech32: Option<char>,
+    bech32_data_only: bool,
+    short: bool,
+    /// Print bare values in `text` output and no batch summary
+    quiet: bool,
+    /// Add the hex token ID to `text` and `csv` output, which `json` and `yaml` always include
+    show_token_id_hex: bool,
+}
+
+/// A result as printed by the CLI, labeled with the remote domain it was computed for, if any,
+/// along with the token ID as hex and optionally the padded token address.
+#[derive(serde::Serialize)]
+struct Row {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    remote_domain: Option<u32>,
+    #[serde(flatten)]
+    result: WarpRouteResult,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    padded_token_address: Option<HexHash>,
+    token_id_hex: HexHash,
+}
+
+/// Like [`WarpRouteResult::compute_with_scheme`], but also returns the raw token ID, so it needn't
+/// be decoded back out of the bech32m string.
+fn compute_with_scheme(
+    scheme: &dyn DerivationScheme,
+    deployer: Address,
+    token_address: Address,
+    decimals: u8,
+    token_name: Option<&str>,
+    hrp: Hrp,
+) -> (WarpRouteResult, HexHash) {
+    let warp_route_id = scheme.warp_route_id(token_address, deployer);
+    let token_name = token_name.map_or_else(|| scheme.token_name(warp_route_id), str::to_owned);
+    let token_id = scheme.token_id(warp_route_id, &token_name, decimals);
+    let result = WarpRouteResult {
+        deployer,
+        token_address,
+        decimals,
+        warp_route_id,
+        token_name,
+        token_id: format_token_id(token_id, hrp),
+    };
+    (result, token_id)
+}
+
+/// Renders `results` in `format`. When `is_list` is set, text output labels each result with
+/// its token address and JSON output is an array; otherwise `results` holds a single result.
+/// Results computed for a `--remote-domain` are labeled with it in every format.
+fn render(
+    results: &[Row],
+    format: OutputFormat,
+    is_list: bool,
+    display: DisplayOptions,
+) -> anyhow::Result<String> {
+    let mut out = String::new();
+    match format {
+        OutputFormat::Text => {
+            for (i, row) in results.iter().enumerate() {
+                if is_list && i > 0 && !display.quiet {
+                    out.push('\n');
+                }
+                write_text(&mut out, row, is_list, display)?;
+            }
+        }
+        OutputFormat::Csv => {
+            let has_domains = results.iter().any(|row| row.remote_domain.is_some());
+            let has_padded = results.iter().any(|row| row.padded_token_address.is_some());
+            writeln!(
+                out,
+                "{}",
+                csv_header(has_domains, has_padded, display.show_token_id_hex)
+            )?;
+            for row in results {
+                writeln!(out, "{}", csv_line(row, display))?;
+            }
+        }
+        OutputFormat::Sql => {
+            for row in results {
+                writeln!(out, "{}", sql_insert(&row.result, display))?;
+            }
+        }
+        OutputFormat::Markdown => {
+            let has_domains = results.iter().any(|row| row.remote_domain.is_some());
+            write!(out, "{}", markdown_header(has_domains))?;
+            for row in results {
+                writeln!(out, "{}", markdown_row(row, display))?;
+            }
+        }
+        OutputFormat::Json | OutputFormat::Yaml => {
+            let mut values = results
+                .iter()
+                .map(|row| row_value(row, display))
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            let value = if is_list {
+                serde_json::Value::Array(values)
+            } else {
+                values.remove(0)
+            };
+            match format {
+                OutputFormat::Yaml => write!(out, "{}", serde_yaml::to_string(&value)?)?,
+                _ => writeln!(out, "{}", serde_json::to_string_pretty(&value)?)?,
+            }
+        }
+    }
+    Ok(out)
+}
+
+/// Writes `row` as labeled lines, including its token address if `label_token` is set.
+fn write_text(
+    out: &mut String,
+    row: &Row,
+    label_token: bool,
+    display: DisplayOptions,
+) -> anyhow::Result<()> {
+    let token_id = match display.group_bech32 {
+        Some(separator) => group_bech32(&row.result.token_id, separator),
+        None => display_token_id(&row.result.token_id, display).to_string(),
+    };
+    if display.quiet {
+        writeln!(out, "{}", row.result.warp_route_id)?;
+        writeln!(out, "{token_id}")?;
+        return Ok(());
+    }
+    if let Some(remote_domain) = row.remote_domain {
+        writeln!(out, "Remote Domain: {remote_domain}")?;
     }
+    if label_token {
+        let token_address = display_address(row.result.token_address, display.trim_leading_zeros);
+        writeln!(out, "Token Address: {token_address}")?;
+    }
+    if let Some(padded) = row.padded_token_address {
+        writeln!(out, "Padded Token Address: {padded}")?;
+    }
+    if display.show_decimals {
+        writeln!(out, "Decimals: {}", row.result.decimals)?;
+    }
+    writeln!(out, "Warp Route ID: {}", row.result.warp_route_id)?;
+    writeln!(out, "Token ID: {token_id}")?;
+    if display.show_token_id_hex {
+        writeln!(out, "Token ID (hex): {}", row.token_id_hex)?;
+    }
+    Ok(())
+}
+
+/// Splits the data part of a bech32 string into groups of 4 characters joined by `separator`,
+/// keeping the prefix and `1` attached to the first group. The result is for display only.
+fn group_bech32(s: &str, separator: char) -> String {
+    let Some(hrp_end) = s.rfind('1') else {
+        return s.to_string();
+    };
+    let (prefix, data) = s.split_at(hrp_end + 1);
+    let groups: Vec<&str> = data
+        .as_bytes()
+        .chunks(4)
+        .map(|chunk| std::str::from_utf8(chunk).expect("bech32 data is ASCII"))
+        .collect();
+    format!(
+        "{prefix}{}",
+        groups.join(separator.encode_utf8(&mut [0; 4]))
+    )
+}
+
+/// Parses a `--group-separator`, rejecting letters and digits.
+fn parse_group_separator(s: &str) -> anyhow::Result<char> {
+    let mut chars = s.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) if !c.is_alphanumeric() => Ok(c),
+        (Some(_), None) => anyhow::bail!("{s} is a letter or digit"),
+        _ => anyhow::bail!("expected a single character, got {s:?}"),
+    }
+}
+
+/// Formats a bech32 `token_id` for output, as only its data part if `--bech32-data-only` is set.
+/// The data part is everything after the last `1`, which can't appear in the data itself.
+fn display_token_id(token_id: &str, display: DisplayOptions) -> &str {
+    match token_id.rfind('1') {
+        Some(separator) if display.bech32_data_only => &token_id[separator + 1..],
+        _ => token_id,
+    }
+}
+
+/// Converts `row` to the JSON value printed by the `json` and `yaml` formats.
+fn row_value(row: &Row, display: DisplayOptions) -> anyhow::Result<serde_json::Value> {
+    let mut value = serde_json::to_value(row)?;
+    if display.trim_leading_zeros {
+        value["deployer"] = row.result.deployer.to_trimmed_string().into();
+        value["token_address"] = row.result.token_address.to_trimmed_string().into();
+    }
+    if display.bech32_data_only {
+        value["token_id"] = display_token_id(&row.result.token_id, display).into();
+    }
+    Ok(value)
+}
+
+const CSV_HEADER: &str = "deployer,token_address,decimals,warp_route_id,token_name,token_id";
+
+/// The CSV header for rows with or without a remote domain, padded token address and hex token
+/// ID.
+fn csv_header(has_domains: bool, has_padded: bool, has_hex: bool) -> String {
+    let mut header = String::new();
+    if has_domains {
+        header.push_str("remote_domain,");
+    }
+    header.push_str(CSV_HEADER);
+    if has_padded {
+        header.push_str(",padded_token_address");
+    }
+    if has_hex {
+        header.push_str(",token_id_hex");
+    }
+    header
+}
+
+/// Formats `row` as a line matching [`csv_header`].
+fn csv_line(row: &Row, display: DisplayOptions) -> String {
+    let mut line = String::new();
+    if let Some(remote_domain) = row.remote_domain {
+        line.push_str(&format!("{},", CsvField(remote_domain)));
+    }
+    line.push_str(&csv_row(&row.result, display));
+    if let Some(padded) = row.padded_token_address {
+        line.push_str(&format!(",{}", CsvField(padded)));
+    }
+    if display.show_token_id_hex {
+        line.push_str(&format!(",{}", CsvField(row.token_id_hex)));
+    }
+    line
+}
+
+/// Formats `result` as a row matching [`CSV_HEADER`].
+fn csv_row(result: &WarpRouteResult, display: DisplayOptions) -> String {
+    format!(
+        "{},{},{},{},{},{}",
+        CsvField(display_address(result.deployer, display.trim_leading_zeros)),
+        CsvField(display_address(
+            result.token_address,
+            display.trim_leading_zeros
+        )),
+        CsvField(result.decimals),
+        CsvField(result.warp_route_id),
+        CsvField(&result.token_name),
+        CsvField(display_token_id(&result.token_id, display)),
+    )
+}
+
+/// Formats `result` as an insert into a table created with:
+///
+/// ```sql
+/// CREATE TABLE warp_routes (
+///     deployer TEXT NOT NULL,
+///     token_address TEXT NOT NULL,
+///     decimals INTEGER NOT NULL,
+///     warp_route_id TEXT NOT NULL,
+///     token_id TEXT NOT NULL
+/// );
+/// ```
+fn sql_insert(result: &WarpRouteResult, display: DisplayOptions) -> String {
+    format!(
+        "INSERT INTO warp_routes (deployer, token_address, decimals, warp_route_id, token_id) VALUES ({}, {}, {}, {}, {});",
+        SqlString(display_address(result.deployer, display.trim_leading_zeros)),
+        SqlString(display_address(
+            result.token_address,
+            display.trim_leading_zeros
+        )),
+        result.decimals,
+        SqlString(result.warp_route_id),
+        SqlString(display_token_id(&result.token_id, display)),
+    )
 }
 
-impl<T> std::fmt::Debug for HexString<T>
-where
-    T: AsRef<[u8]>,
-{
+/// Formats the wrapped value as a single-quoted SQL string literal, doubling any embedded quotes.
+struct SqlString<T>(T);
+
+impl<T: Display> Display for SqlString<T> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "0x{}", hex::encode(&self.0))
+        write!(f, "'{}'", self.0.to_string().replace('\'', "''"))
     }
 }
 
-/// [`serde`] (de)serialization functions for [`HexString`], to be used with
-/// `#[serde(with = "...")]`.
-pub mod hex_string_serde {
-    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+/// The header and delimiter rows of the `markdown` table, with a remote domain column if
+/// `has_domains` is set.
+fn markdown_header(has_domains: bool) -> String {
+    let domain = if has_domains { "Remote Domain | " } else { "" };
+    let delimiter = if has_domains { "--- | " } else { "" };
+    format!(
+        "| {domain}Token Address | Decimals | Warp Route ID | Token ID |\n| {delimiter}--- | --: | --- | --- |\n"
+    )
+}
 
-    use super::HexString;
+/// Formats `row` as a row of the table started by [`markdown_header`].
+fn markdown_row(row: &Row, display: DisplayOptions) -> String {
+    let shorten = |s: String| {
+        if display.short && s.len() > 20 {
+            format!("{}...{}", &s[..10], &s[s.len() - 8..])
+        } else {
+            s
+        }
+    };
+    let domain = match row.remote_domain {
+        Some(remote_domain) => format!("{remote_domain} | "),
+        None => String::new(),
+    };
+    format!(
+        "| {domain}`{}` | {} | `{}` | `{}` |",
+        shorten(display_address(
+            row.result.token_address,
+            display.trim_leading_zeros
+        )),
+        row.result.decimals,
+        shorten(row.result.warp_route_id.to_string()),
+        display_token_id(&row.result.token_id, display),
+    )
+}
 
-    /// Serializes `data` as hex string using lowercase characters and prefixing with '0x'.
-    ///
-    /// Lowercase characters are used (e.g. `f9b4ca`). The resulting string's length
-    /// is always even, each byte in data is always encoded using two hex digits.
-    /// Thus, the resulting string contains exactly twice as many bytes as the input
-    /// data.
-    pub fn serialize<S, T>(data: T, serializer: S) -> Result<S::Ok, S::Error>
-    where
-        S: Serializer,
-        T: AsRef<[u8]>,
+/// Fails if a batch of `len` entries exceeds `--max-batch-size`.
+fn check_batch_size(len: usize, max_batch_size: Option<usize>) -> anyhow::Result<()> {
+    if let Some(max) = max_batch_size
+        && len > max
     {
-        HexString::<T>(data).serialize(serializer)
+        anyhow::bail!("Batch has {len} entries, more than --max-batch-size {max}");
+    }
+    Ok(())
+}
+
+/// Formats `address` for output, without leading zero bytes if `trim` is set.
+fn display_address(address: Address, trim: bool) -> String {
+    if trim {
+        address.to_trimmed_string()
+    } else {
+        address.to_string()
+    }
+}
+
+/// The subset of the [Uniswap token list](https://tokenlists.org) format needed to compute
+/// warp routes. Other fields are ignored.
+#[derive(serde::Deserialize)]
+struct TokenList {
+    tokens: Vec<TokenListEntry>,
+}
+
+#[derive(serde::Deserialize)]
+struct TokenListEntry {
+    address: Address,
+    decimals: u8,
+}
+
+/// The subset of a Hyperlane warp route config (the registry's `WarpCoreConfig`) needed to
+/// compute warp routes. Other fields are ignored.
+#[derive(serde::Deserialize)]
+struct HyperlaneConfig {
+    tokens: Vec<HyperlaneToken>,
+}
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct HyperlaneToken {
+    chain_name: String,
+    standard: String,
+    decimals: u8,
+    collateral_address_or_denom: Option<String>,
+}
+
+/// Defaults for the command line flags, read from `--config`.
+#[derive(Default, serde::Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+struct Config {
+    deployer: Option<String>,
+    token_address: Option<String>,
+}
+
+impl Config {
+    /// Fails, naming the field, if an address that is set doesn't parse with `input_format`.
+    fn validate(&self, input_format: InputFormat) -> anyhow::Result<()> {
+        for (field, value) in [
+            ("deployer", &self.deployer),
+            ("token-address", &self.token_address),
+        ] {
+            if let Some(value) = value {
+                input_format
+                    .parse_address(value)
+                    .map_err(|e| anyhow::anyhow!("field `{field}`: {e}"))?;
+            }
+        }
+        Ok(())
     }
 
-    /// Deserializes a hex string into raw bytes.
-    ///
-    /// Both upper and lower case characters are valid in the input string and can
-    /// even be mixed.
-    pub fn deserialize<'de, D, T>(deserializer: D) -> Result<T, D::Error>
-    where
-        D: Deserializer<'de>,
-        T: TryFrom<Vec<u8>> + AsRef<[u8]>,
+    /// Takes each field from `self`, falling back to `defaults` where it's unset.
+    fn or(self, defaults: Config) -> Config {
+        Config {
+            deployer: self.deployer.or(defaults.deployer),
+            token_address: self.token_address.or(defaults.token_address),
+        }
+    }
+}
+
+/// Reads the `--config` file at `path` as `format`, or by its extension if `format` is `None`:
+/// `.yaml` and `.yml` files are YAML and everything else is JSON.
+/// Each address is checked against `input_format`, so an invalid one is reported with its field
+/// name.
+fn read_config(
+    path: &Path,
+    format: Option<ConfigFormat>,
+    input_format: InputFormat,
+) -> anyhow::Result<Config> {
+    let format = format.unwrap_or_else(|| ConfigFormat::from_path(path));
+    let contents = read_input(path)?;
+    let config = match format {
+        ConfigFormat::Json => serde_json::from_str(&contents).map_err(anyhow::Error::from),
+        ConfigFormat::Yaml => serde_yaml::from_str(&contents).map_err(anyhow::Error::from),
+    };
+    config
+        .and_then(|config: Config| {
+            config.validate(input_format)?;
+            Ok(config)
+        })
+        .map_err(|e| anyhow::anyhow!("Failed to parse config {}, error: {}", path.display(), e))
+}
+
+/// Reads the contents of `path`, or of stdin if `path` is `-`, with surrounding whitespace
+/// trimmed. All file and stdin inputs go through this, so a trailing newline (e.g. from `echo`)
+/// never reaches a parser.
+fn read_input(path: &Path) -> anyhow::Result<String> {
+    let contents = if path == Path::new("-") {
+        std::io::read_to_string(std::io::stdin())
+    } else {
+        std::fs::read_to_string(path)
+    };
+    contents
+        .map(|contents| contents.trim().to_string())
+        .map_err(|e| anyhow::anyhow!("Failed to read {}, error: {}", path.display(), e))
+}
+
+fn read_token_list(path: &Path) -> anyhow::Result<TokenList> {
+    let contents = read_input(path)?;
+    serde_json::from_str(&contents).map_err(|e| {
+        anyhow::anyhow!(
+            "Failed to parse token list {}, error: {}",
+            path.display(),
+            e
+        )
+    })
+}
+
+/// Reads the token address and decimals of every EVM token in a Hyperlane warp route config.
+/// Native tokens (`EvmHypNative...` standards) use [`NATIVE_TOKEN_ADDRESS`], other EVM tokens
+/// their `collateralAddressOrDenom`. Tokens without one, such as synthetics and tokens on
+/// non-EVM chains, wrap no EVM token and are skipped with a warning.
+fn read_hyperlane_config(path: &Path, quiet: bool) -> anyhow::Result<Vec<(Address, u8)>> {
+    let contents = read_input(path)?;
+    // YAML is a superset of JSON, so this parses both.
+    let config: HyperlaneConfig = serde_yaml::from_str(&contents).map_err(|e| {
+        anyhow::anyhow!(
+            "Failed to parse Hyperlane config {}, error: {}",
+            path.display(),
+            e
+        )
+    })?;
+    let mut inputs = Vec::new();
+    for token in config.tokens {
+        let address = match &token.collateral_address_or_denom {
+            _ if token.standard.starts_with("EvmHypNative") => NATIVE_TOKEN_ADDRESS,
+            Some(collateral) if token.standard.starts_with("Evm") => {
+                collateral.parse().map_err(|e| {
+                    anyhow::anyhow!(
+                        "Invalid collateralAddressOrDenom {} on {}, error: {}",
+                        collateral,
+                        token.chain_name,
+                        e
+                    )
+                })?
+            }
+            _ => {
+                if !quiet {
+                    eprintln!(
+                        "Warning: skipping the {} token on {}, which wraps no EVM token",
+                        token.standard, token.chain_name
+                    );
+                }
+                continue;
+            }
+        };
+        inputs.push((address, token.decimals));
+    }
+    Ok(inputs)
+}
+
+/// Reads the `contractAddress` of the transaction receipt JSON at `path`, which may be wrapped in
+/// a JSON-RPC response's `result`.
+fn read_receipt_address(path: &Path) -> anyhow::Result<Address> {
+    let contents = read_input(path)?;
+    let value: serde_json::Value = serde_json::from_str(&contents)
+        .map_err(|e| anyhow::anyhow!("Failed to parse receipt {}, error: {}", path.display(), e))?;
+    let receipt = value.get("result").unwrap_or(&value);
+    let address = match receipt.get("contractAddress") {
+        Some(serde_json::Value::String(address)) => address,
+        Some(serde_json::Value::Null) | None => anyhow::bail!(
+            "Receipt {} has no contractAddress, so its transaction didn't create a contract",
+            path.display()
+        ),
+        Some(other) => anyhow::bail!(
+            "Receipt {} has a contractAddress that isn't a string: {}",
+            path.display(),
+            other
+        ),
+    };
+    address.parse().map_err(|e| {
+        anyhow::anyhow!(
+            "Invalid contractAddress {} in receipt {}, error: {}",
+            address,
+            path.display(),
+            e
+        )
+    })
+}
+
+/// Formats the wrapped value as a single CSV field, quoting it (and doubling any embedded
+/// quotes) if it contains a comma, a quote or a line break.
+struct CsvField<T>(T);
+
+impl<T: Display> Display for CsvField<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let field = self.0.to_string();
+        if field.contains([',', '"', '\n', '\r']) {
+            write!(f, "\"{}\"", field.replace('"', "\"\""))
+        } else {
+            f.write_str(&field)
+        }
+    }
+}
+
+/// Parses a 32-byte value given either as hex or as a bech32(m) string with any prefix. Both
+/// are case-insensitive, and callers compare the decoded bytes rather than the strings.
+fn parse_hash_or_bech32(s: &str) -> anyhow::Result<HexHash> {
+    if !s.starts_with("0x")
+        && let Ok((_, bytes)) = bech32::decode(s)
     {
-        HexString::<T>::deserialize(deserializer).map(|s| s.0)
+        return Ok(HexString(bytes.try_into().map_err(|bytes: Vec<u8>| {
+            anyhow::anyhow!("Expected 32 bytes, got {}", bytes.len())
+        })?));
+    }
+    s.parse()
+}
+
+/// Computes the token ID of every entry in the `--batch` style file at `path`, keyed by token
+/// address. Fails on the first line that doesn't parse or on a repeated token address.
+fn read_token_ids(
+    path: &Path,
+    deployer: Address,
+) -> anyhow::Result<std::collections::BTreeMap<Address, String>> {
+    let batch = Batch {
+        path,
+        input_format: InputFormat::Hex,
+        skip: 0,
+        take: None,
+        max_batch_size: None,
+        fail_fast: true,
+    };
+    let mut token_ids = std::collections::BTreeMap::new();
+    for entry in batch.entries()? {
+        let (line_number, line) = entry?;
+        let (token_address, decimals) = batch
+            .parse(&line)
+            .map_err(|e| anyhow::anyhow!("Line {line_number} of {}: {e}", path.display()))?;
+        let result = WarpRouteResult::compute(deployer, token_address, decimals, token_hrp());
+        if token_ids.insert(token_address, result.token_id).is_some() {
+            anyhow::bail!(
+                "Line {line_number} of {}: token address {token_address} appears more than once",
+                path.display()
+            );
+        }
+    }
+    Ok(token_ids)
+}
+
+/// Prints a `change,token_address,old_token_id,new_token_id` CSV row, ordered by token address,
+/// for every token that is only in `old`, only in `new`, or has a different token ID in each.
+/// Unchanged tokens are omitted and the missing side of an added or removed token is empty.
+fn compare(
+    old: &Path,
+    new: &Path,
+    old_deployer: Address,
+    new_deployer: Address,
+) -> anyhow::Result<()> {
+    let old = read_token_ids(old, old_deployer)?;
+    let mut new = read_token_ids(new, new_deployer)?;
+    let mut changes = std::collections::BTreeMap::new();
+    for (token_address, old_id) in old {
+        match new.remove(&token_address) {
+            Some(new_id) if new_id == old_id => {}
+            Some(new_id) => {
+                changes.insert(token_address, ("changed", old_id, new_id));
+            }
+            None => {
+                changes.insert(token_address, ("removed", old_id, String::new()));
+            }
+        }
+    }
+    for (token_address, new_id) in new {
+        changes.insert(token_address, ("added", String::new(), new_id));
+    }
+    let mut out = String::from("change,token_address,old_token_id,new_token_id\n");
+    for (token_address, (change, old_id, new_id)) in &changes {
+        writeln!(out, "{change},{token_address},{old_id},{new_id}")?;
+    }
+    write_stdout(&out)?;
+    eprintln!("{} tokens differ", changes.len());
+    Ok(())
+}
+
+/// A row of a `derive-batch` file.
+#[derive(serde::Deserialize)]
+struct DeriveBatchRow {
+    deployer: Address,
+    token_address: Address,
+    #[serde(default = "default_decimals")]
+    decimals: u8,
+    token_name: Option<String>,
+}
+
+fn default_decimals() -> u8 {
+    18
+}
+
+/// Reads the rows of the `derive-batch` file at `path`, each labeled with its line for CSV or its
+/// 1-based index for JSON. Rows are parsed independently, so a bad row only fails itself.
+fn read_derive_batch(
+    path: &Path,
+    format: Option<RowsFormat>,
+) -> anyhow::Result<Vec<(String, anyhow::Result<DeriveBatchRow>)>> {
+    let contents = read_input(path)?;
+    let format = format.unwrap_or(match path.extension() {
+        Some(extension) if extension == "json" => RowsFormat::Json,
+        _ => RowsFormat::Csv,
+    });
+    match format {
+        // An empty file has no rows, like an empty CSV file.
+        RowsFormat::Json if contents.trim().is_empty() => Ok(Vec::new()),
+        RowsFormat::Json => {
+            let rows: Vec<serde_json::Value> = serde_json::from_str(&contents).map_err(|e| {
+                anyhow::anyhow!("Failed to parse batch {}, error: {}", path.display(), e)
+            })?;
+            Ok(rows
+                .into_iter()
+                .enumerate()
+                .map(|(i, row)| {
+                    let label = format!("Row {}", i + 1);
+                    (label, serde_json::from_value(row).map_err(Into::into))
+                })
+                .collect())
+        }
+        RowsFormat::Csv => {
+            let mut lines = contents.lines().enumerate();
+            let Some((_, header)) = lines.next() else {
+                return Ok(Vec::new());
+            };
+            let columns = split_csv_line(header).map_err(|e| {
+                anyhow::anyhow!("Invalid header in batch {}, error: {}", path.display(), e)
+            })?;
+            Ok(lines
+                .filter(|(_, line)| !line.trim().is_empty())
+                .map(|(i, line)| (format!("Line {}", i + 1), parse_csv_row(&columns, line)))
+                .collect())
+        }
+    }
+}
+
+/// Splits a CSV `line` into its fields, the inverse of [`CsvField`]. A field wrapped in double
+/// quotes may contain commas, with `""` standing for a quote; other fields are trimmed. A quoted
+/// field can't span lines.
+fn split_csv_line(line: &str) -> anyhow::Result<Vec<String>> {
+    let mut fields = Vec::new();
+    let mut chars = line.chars().peekable();
+    loop {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        let mut field = String::new();
+        if chars.next_if_eq(&'"').is_some() {
+            loop {
+                match chars.next() {
+                    Some('"') if chars.next_if_eq(&'"').is_some() => field.push('"'),
+                    Some('"') => break,
+                    Some(c) => field.push(c),
+                    None => anyhow::bail!("Unterminated quote in field {}", fields.len() + 1),
+                }
+            }
+            while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        } else {
+            while let Some(c) = chars.next_if(|&c| c != ',') {
+                field.push(c);
+            }
+            field.truncate(field.trim_end().len());
+        }
+        fields.push(field);
+        match chars.next() {
+            None => return Ok(fields),
+            Some(',') => {}
+            Some(c) => anyhow::bail!(
+                "Unexpected {c:?} after the closing quote of field {}",
+                fields.len()
+            ),
+        }
+    }
+}
+
+/// Parses a CSV `line` whose fields are named by `columns`.
+fn parse_csv_row(columns: &[String], line: &str) -> anyhow::Result<DeriveBatchRow> {
+    let fields = split_csv_line(line)?;
+    if fields.len() != columns.len() {
+        anyhow::bail!("Expected {} fields, got {}", columns.len(), fields.len());
+    }
+    let field = |name: &str| {
+        columns
+            .iter()
+            .position(|column| column == name)
+            .map(|i| fields[i].as_str())
+            .filter(|field| !field.is_empty())
+    };
+    let address = |name: &str| -> anyhow::Result<Address> {
+        let value = field(name).ok_or_else(|| anyhow::anyhow!("Missing {name}"))?;
+        value
+            .parse()
+            .map_err(|e| anyhow::anyhow!("Invalid {name} {value}, error: {e}"))
+    };
+    let decimals = match field("decimals") {
+        Some(decimals) => decimals
+            .parse()
+            .map_err(|e| anyhow::anyhow!("Invalid decimals {decimals}, error: {e}"))?,
+        None => default_decimals(),
+    };
+    Ok(DeriveBatchRow {
+        deployer: address("deployer")?,
+        token_address: address("token_address")?,
+        decimals,
+        token_name: field("token_name").map(str::to_string),
+    })
+}
+
+/// Computes the warp route of every row of the `derive-batch` file at `input` and writes the
+/// results in `format` to `output`, or stdout. Rows that fail to parse are reported on stderr and
+/// skipped; the command then fails after writing the other results.
+fn derive_batch(
+    input: &Path,
+    input_format: Option<RowsFormat>,
+    output: Option<&Path>,
+    no_clobber: bool,
+    format: OutputFormat,
+    hrp: Hrp,
+    max_batch_size: Option<usize>,
+) -> anyhow::Result<()> {
+    let rows = read_derive_batch(input, input_format)?;
+    let total = rows.len();
+    check_batch_size(total, max_batch_size)?;
+    let mut results = Vec::new();
+    for (label, row) in rows {
+        let row = match row {
+            Ok(row) => row,
+            Err(e) => {
+                eprintln!("{label}: {e}");
+                continue;
+            }
+        };
+        let (result, token_id_hex) = compute_with_scheme(
+            &SovV1Scheme,
+            row.deployer,
+            row.token_address,
+            row.decimals,
+            row.token_name.as_deref(),
+            hrp,
+        );
+        results.push(Row {
+            remote_domain: None,
+            result,
+            padded_token_address: None,
+            token_id_hex,
+        });
+    }
+    let display = DisplayOptions {
+        trim_leading_zeros: false,
+        show_decimals: false,
+        group_bech32: None,
+        bech32_data_only: false,
+        short: false,
+        quiet: false,
+        show_token_id_hex: false,
+    };
+    let rendered = render(&results, format, true, display)?;
+    match output {
+        Some(path) => write_output(path, &rendered, no_clobber).map_err(|e| {
+            anyhow::anyhow!("Failed to write output {}, error: {}", path.display(), e)
+        })?,
+        None => write_stdout(&rendered)?,
+    }
+    let failed = total - results.len();
+    eprintln!("Computed {} entries, {failed} failed", results.len());
+    if failed > 0 {
+        anyhow::bail!("Failed to parse {failed} of {total} batch rows");
+    }
+    Ok(())
+}
+
+/// Runs every known-answer vector, failing if any of them don't match.
+fn conformance(path: &Path) -> anyhow::Result<()> {
+    let contents = read_input(path)?;
+    let vectors: Vec<test_vectors::TestVector<String>> =
+        serde_json::from_str(&contents).map_err(|e| {
+            anyhow::anyhow!(
+                "Failed to parse reference vectors {}, error: {}",
+                path.display(),
+                e
+            )
+        })?;
+    for (i, vector) in vectors.iter().enumerate() {
+        if let Err(e) = vector.check() {
+            let mut out = format!("FAIL vector {i}: {e}\n");
+            writeln!(out, "  Deployer: {}", vector.deployer)?;
+            writeln!(out, "  Token Address: {}", vector.token_address)?;
+            writeln!(out, "  Decimals: {}", vector.decimals)?;
+            write_stdout(&out)?;
+            anyhow::bail!(
+                "Vector {i} of {} doesn't match the reference implementation",
+                vectors.len()
+            );
+        }
+    }
+    write_stdout(&format!("All {} reference vectors passed\n", vectors.len()))
+}
+
+fn man() -> anyhow::Result<()> {
+    let mut page = Vec::new();
+    clap_mangen::Man::new(Args::command()).render(&mut page)?;
+    write_stdout(&String::from_utf8(page)?)
+}
+
+fn selftest() -> anyhow::Result<()> {
+    let mut failures = 0;
+    let mut out = String::new();
+    for (i, vector) in test_vectors::VECTORS.iter().enumerate() {
+        match vector.check() {
+            Ok(()) => writeln!(out, "PASS vector {i}")?,
+            Err(e) => {
+                failures += 1;
+                writeln!(out, "FAIL vector {i}: {e}")?;
+            }
+        }
+    }
+    write_stdout(&out)?;
+    if failures > 0 {
+        anyhow::bail!(
+            "{failures} of {} known-answer vectors failed",
+            test_vectors::VECTORS.len()
+        );
     }
+    write_stdout(&format!(
+        "All {} known-answer vectors passed\n",
+        test_vectors::VECTORS.len()
+    ))
 }
 
-fn parse_vec_u8(s: &str) -> anyhow::Result<Vec<u8>> {
-    let s = s.strip_prefix("0x").unwrap_or(s);
+/// Prints the SHA-256 hash of `preimage`, reading it as hex from stdin if not provided.
+fn hash(preimage: Option<HexString>) -> anyhow::Result<()> {
+    let preimage = match preimage {
+        Some(preimage) => preimage,
+        None => read_input(Path::new("-"))?.parse()?,
+    };
+    let digest: HexHash = HexString(Sha256::digest(&preimage.0).into());
+    write_stdout(&format!("{digest}\n"))
+}
+
+fn normalize(values: Vec<String>, group_separator: Option<char>) -> anyhow::Result<()> {
+    let values = if values.is_empty() {
+        let input = read_input(Path::new("-"))?;
+        input
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(str::to_string)
+            .collect()
+    } else {
+        values
+    };
+    let mut out = String::new();
+    for value in values {
+        writeln!(out, "{}", normalize_value(&value, group_separator)?)?;
+    }
+    write_stdout(&out)
+}
+
+/// The canonical form of an address, hash or token ID, detected like [`inspect`] does.
+fn normalize_value(value: &str, group_separator: Option<char>) -> anyhow::Result<String> {
+    let mut compact: String = value.split_whitespace().collect();
+    if let Some(separator) = group_separator {
+        // Groups only split a token ID's data, which follows the last `1`, so a separator that
+        // also appears in the prefix, like `_`, is kept there.
+        let data_start = match compact.to_ascii_lowercase().starts_with("token_") {
+            true => compact.rfind('1').map_or(0, |i| i + 1),
+            false => 0,
+        };
+        let data: String = compact[data_start..]
+            .chars()
+            .filter(|&c| c != separator)
+            .collect();
+        compact.replace_range(data_start.., &data);
+    }
+    if compact.to_ascii_lowercase().starts_with("token_") {
+        let token_id = parse_token_id(&compact)
+            .map_err(|e| anyhow::anyhow!("Failed to normalize {}, error: {}", compact, e))?;
+        return Ok(format_token_id(token_id, token_hrp()));
+    }
+    let hex = compact.strip_prefix("0X").unwrap_or(&compact);
+    let HexString(bytes) = hex
+        .parse::<HexString>()
+        .map_err(|e| anyhow::anyhow!("Failed to normalize {}, error: {}", compact, e))?;
+    match bytes.len() {
+        20 | 32 => Ok(HexString(bytes).to_string()),
+        len => anyhow::bail!(
+            "Failed to normalize {compact}, error: expected a 20-byte address, a 32-byte hash or a token ID, got {len} bytes"
+        ),
+    }
+}
+
+/// Prints what kind of identifier `value` is, along with its decoded bytes.
+///
+/// 20-byte hex values are reported as addresses, 32-byte hex values as hashes, and
+/// `token_...` strings are decoded as bech32m token IDs.
+fn inspect(value: &str, hrp_allowlist: &[Hrp]) -> anyhow::Result<()> {
+    let bech32 = if value.starts_with("0x") {
+        None
+    } else {
+        bech32::decode(value).ok()
+    };
+    let (kind, bytes) = if let Some((hrp, data)) = bech32 {
+        if !hrp_allowlist.contains(&hrp) {
+            let allowed: Vec<_> = hrp_allowlist.iter().map(Hrp::to_string).collect();
+            anyhow::bail!(
+                "Rejected bech32 value with prefix {hrp}, which is not in --hrp-allowlist {}",
+                allowed.join(",")
+            );
+        }
+        if hrp == token_hrp() {
+            ("Token ID", parse_token_id(value)?.to_string())
+        } else {
+            ("Bech32 value", HexString(data).to_string())
+        }
+    } else if value.starts_with("token_") {
+        ("Token ID", parse_token_id(value)?.to_string())
+    } else {
+        let HexString(bytes) = value.parse::<HexString>()?;
+        match bytes.len() {
+            20 => ("Address", HexString(bytes).to_string()),
+            32 => ("Hash", HexString(bytes).to_string()),
+            len => anyhow::bail!(
+                "Unrecognized value: expected a 20-byte address, a 32-byte hash or a token ID, got {len} bytes"
+            ),
+        }
+    };
+    write_stdout(&format!("Type: {kind}\nBytes: {bytes}\n"))
+}
 
-    hex::decode(s).map_err(|e| anyhow::anyhow!("Failed to decode hex string {}, error: {}", s, e))
+/// One step of the `sov-v1` derivation, as printed by `--explain` and `--explain-json`.
+#[derive(serde::Serialize)]
+struct Step {
+    step: &'static str,
+    description: String,
+    bytes: HexString,
 }
 
-fn main() {
-    let Args {
+/// The steps deriving the warp route ID and token ID of `token_address`, in order.
+fn derivation_steps(deployer: Address, token_address: Address, decimals: u8) -> Vec<Step> {
+    let step = |step, description: &str, bytes: &[u8]| Step {
+        step,
+        description: description.to_string(),
+        bytes: HexString(bytes.to_vec()),
+    };
+    let padded = HexHash::from(token_address);
+    // Hash through the library's preimage builders, keeping each hash to show the token hash
+    // before its final byte is replaced.
+    let mut hashes = Vec::new();
+    let (warp_route_id, token_name, token_id) = derive_with_hash_fn(
         deployer,
         token_address,
-    } = Args::parse();
+        decimals,
+        get_token_name,
+        |preimage| {
+            let hash: [u8; 32] = Sha256::digest(preimage).into();
+            hashes.push(hash);
+            Ok::<_, std::convert::Infallible>(hash)
+        },
+    )
+    .unwrap_or_else(|never| match never {});
+    let token_hash = hashes[1];
+    vec![
+        step(
+            "padded_token_address",
+            "The token address, left-padded with zeros to 32 bytes",
+            &padded.0,
+        ),
+        step("separator", "A single zero byte", &[SEPARATOR_BYTE]),
+        step("deployer", "The deployer's 20 raw bytes", &deployer.0),
+        step(
+            "warp_route_id",
+            "SHA-256 of the padded token address, separator and deployer",
+            &warp_route_id.0,
+        ),
+        step(
+            "token_name",
+            &format!("The UTF-8 token name \"{token_name}\""),
+            token_name.as_bytes(),
+        ),
+        step("decimals", "The decimals, as a single byte", &[decimals]),
+        step(
+            "token_hash",
+            "SHA-256 of the warp route ID, token name and decimals",
+            &token_hash,
+        ),
+        step(
+            "token_id",
+            "The token hash with its final byte replaced by the decimals",
+            &token_id.0,
+        ),
+    ]
+}
 
-    let warp_route_id = get_warp_route_id(token_address, deployer);
+/// Prints the inputs, the padded token address, the token name and both IDs in every encoding.
+/// `unit` is printed alongside the decimals but never hashed.
+fn derive_all(
+    deployer: Address,
+    token_address: Address,
+    decimals: u8,
+    unit: Option<Unit>,
+    warp_route_hrp: Option<String>,
+) -> anyhow::Result<()> {
+    let warp_route_hrp = warp_route_hrp
+        .map(|hrp| resolve_hrp(&normalize_hrp(hrp), None))
+        .transpose()?;
+    let (warp_route_id, token_id) = derive(deployer, token_address, decimals);
+    let mut out = String::new();
+    writeln!(out, "Deployer: {deployer}")?;
+    writeln!(out, "Token Address: {token_address}")?;
+    writeln!(
+        out,
+        "Padded Token Address: {}",
+        HexHash::from(token_address)
+    )?;
+    writeln!(out, "Decimals: {decimals}")?;
+    if let Some(unit) = unit {
+        let name = unit.to_possible_value().expect("no units are skipped");
+        writeln!(
+            out,
+            "Unit: {} ({} decimals)",
+            name.get_name(),
+            unit.decimals()
+        )?;
+        if unit.decimals() != decimals {
+            eprintln!(
+                "Warning: {} has {} decimals but the token ID is computed with --decimals {decimals}",
+                name.get_name(),
+                unit.decimals()
+            );
+        }
+    }
+    writeln!(out, "Token Name: {}", get_token_name(warp_route_id))?;
+    writeln!(out, "Warp Route ID: {warp_route_id}")?;
+    if let Some(hrp) = warp_route_hrp {
+        writeln!(
+            out,
+            "Warp Route ID (bech32m): {}",
+            format_token_id(warp_route_id, hrp)
+        )?;
+    }
+    writeln!(out, "Token ID (hex): {token_id}")?;
+    writeln!(out, "Token ID: {}", format_token_id(token_id, token_hrp()))?;
+    write_stdout(&out)
+}
 
-    let token_id = get_token_id(warp_route_id, 18);
-    println!("Warp Route ID: {warp_route_id}",);
-    println!("Token ID: {}", format_token_id(token_id));
+/// The body of a `POST /compute` request.
+#[cfg(feature = "server")]
+#[derive(serde::Deserialize)]
+struct ComputeRequest {
+    deployer: Address,
+    token_address: Address,
+    decimals: u8,
 }
 
-/// `remote_token_id_bytes || 0 || DEPLOYER_ADDRESS`
-fn get_warp_route_id(token_address: Address, deployer: Address) -> HexHash {
-    let mut hasher = Sha256::default();
-    let mut extended_token_address = [0u8; 32];
-    extended_token_address[12..].copy_from_slice(&token_address.0);
-    hasher.update(&extended_token_address);
-    hasher.update(&[0]);
-    hasher.update(&deployer.0);
-    HexString(hasher.finalize().into())
+/// The largest `POST /compute` body read, in bytes. A valid request is well under 200 bytes.
+#[cfg(feature = "server")]
+const MAX_REQUEST_BODY: u64 = 16 * 1024;
+
+/// Answers `POST /compute` requests on `bind:port` until the process is stopped.
+#[cfg(feature = "server")]
+fn serve(bind: std::net::IpAddr, port: u16) -> anyhow::Result<()> {
+    use std::io::Read as _;
+
+    let server = tiny_http::Server::http((bind, port))
+        .map_err(|e| anyhow::anyhow!("Failed to listen on {}:{}, error: {}", bind, port, e))?;
+    eprintln!("Listening on {bind}:{port}");
+    for mut request in server.incoming_requests() {
+        let (status, body) = match (request.method(), request.url()) {
+            (tiny_http::Method::Post, "/compute") => {
+                let mut body = String::new();
+                // Read one byte past the limit, so an oversized body is detected without
+                // buffering all of it.
+                let mut reader = request.as_reader().take(MAX_REQUEST_BODY + 1);
+                match reader.read_to_string(&mut body) {
+                    Ok(_) if body.len() as u64 > MAX_REQUEST_BODY => (
+                        413,
+                        format!("The request body is larger than {MAX_REQUEST_BODY} bytes"),
+                    ),
+                    Ok(_) => compute_response(&body),
+                    Err(e) => (400, format!("Failed to read request body, error: {e}")),
+                }
+            }
+            (_, "/compute") => (405, "Use POST /compute".to_string()),
+            _ => (404, "Not found".to_string()),
+        };
+        let content_type = if status == 200 {
+            "application/json"
+        } else {
+            "text/plain; charset=utf-8"
+        };
+        let header = tiny_http::Header::from_bytes("Content-Type", content_type)
+            .expect("the header is valid");
+        let response = tiny_http::Response::from_string(body)
+            .with_status_code(status)
+            .with_header(header);
+        if let Err(e) = request.respond(response) {
+            eprintln!("Failed to send response, error: {e}");
+        }
+    }
+    Ok(())
 }
 
-/// WARP_ROUTE_ID || "Synthetic token for 0x{hex(WARP_ROUTE_ID)} || {LOCAL_DECIMALS as u8}
-fn get_token_id(warp_route_id: HexHash, decimals: u8) -> HexHash {
-    let mut hasher = Sha256::default();
-    let token_name = format!("Synthetic token for {warp_route_id}");
-    hasher.update(&warp_route_id.0);
-    hasher.update(token_name.as_bytes());
-    hasher.update(&[decimals]);
-    let mut foo: [u8; 32] = hasher.finalize().into();
-    foo[31] = decimals;
-    HexString(foo)
+/// The status code and body answering a `POST /compute` request with `body`.
+#[cfg(feature = "server")]
+fn compute_response(body: &str) -> (u16, String) {
+    let request: ComputeRequest = match serde_json::from_str(body) {
+        Ok(request) => request,
+        Err(e) => return (400, format!("Invalid request, error: {e}")),
+    };
+    let result = WarpRouteResult::compute(
+        request.deployer,
+        request.token_address,
+        request.decimals,
+        token_hrp(),
+    );
+    match serde_json::to_string(&result) {
+        Ok(json) => (200, json),
+        Err(e) => (500, format!("Failed to serialize result, error: {e}")),
+    }
+}
+
+/// The ENS registry, deployed at the same address on mainnet and the official testnets.
+#[cfg(feature = "ens")]
+const ENS_REGISTRY: &str = "0x00000000000C2E074eC69A0dFb2997BA6C7d2e1e";
+
+/// Resolves the ENS `name` to an address by asking the registry at `rpc` for the name's resolver
+/// and the resolver for its `addr`. Fails if either is unset. Names are lowercased, but not
+/// otherwise normalized.
+#[cfg(feature = "ens")]
+fn resolve_ens_name(rpc: &str, name: &str) -> anyhow::Result<Address> {
+    let node = ens_namehash(&name.to_lowercase());
+    let registry: Address = ENS_REGISTRY.parse()?;
+    // `resolver(bytes32)`
+    let resolver = eth_call_address(rpc, registry, [0x01, 0x78, 0xb8, 0xbf], node)?;
+    if resolver.0 == [0; 20] {
+        anyhow::bail!("Failed to resolve ENS name {name}, error: it has no resolver");
+    }
+    // `addr(bytes32)`
+    let address = eth_call_address(rpc, resolver, [0x3b, 0x3b, 0x57, 0xde], node)?;
+    if address.0 == [0; 20] {
+        anyhow::bail!("Failed to resolve ENS name {name}, error: it resolves to the zero address");
+    }
+    Ok(address)
 }
 
-fn format_token_id(id: HexHash) -> String {
-    let prefix = Hrp::parse("token_").expect("token_ is a valid prefix");
-    bech32::encode::<Bech32m>(prefix, &id.0).expect("Failed to format bech32")
+/// The ENS namehash of `name`: the keccak256 of each label folded in from the last one.
+#[cfg(feature = "ens")]
+fn ens_namehash(name: &str) -> [u8; 32] {
+    use sha3::{Digest, Keccak256};
+
+    let mut node = [0u8; 32];
+    for label in name.rsplit('.') {
+        let mut hasher = Keccak256::new();
+        hasher.update(node);
+        hasher.update(Keccak256::digest(label.as_bytes()));
+        node = hasher.finalize().into();
+    }
+    node
+}
+
+/// Calls the function with `selector` on the contract at `to` with a single `bytes32` argument,
+/// and decodes the result as an address.
+#[cfg(feature = "ens")]
+fn eth_call_address(
+    rpc: &str,
+    to: Address,
+    selector: [u8; 4],
+    argument: [u8; 32],
+) -> anyhow::Result<Address> {
+    let data = HexString([&selector[..], &argument].concat());
+    let request = serde_json::json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": "eth_call",
+        "params": [{"to": to.to_string(), "data": data.to_string()}, "latest"],
+    });
+    let response: serde_json::Value = ureq::post(rpc)
+        .send_json(&request)
+        .and_then(|response| response.into_body().read_json())
+        .map_err(|e| anyhow::anyhow!("Failed to call {}, error: {}", rpc, e))?;
+    if let Some(error) = response.get("error") {
+        anyhow::bail!("Failed to call {}, error: {}", rpc, error);
+    }
+    let result = response["result"]
+        .as_str()
+        .ok_or_else(|| anyhow::anyhow!("Failed to call {}, error: no result", rpc))?;
+    let word = HexString::parse_exact::<32>(result)?;
+    Address::from_h256(word.0)
+}
+
+/// Substitutes `remote_domain` for any `{domain}` placeholder in `template` and parses the
+/// result as a bech32 HRP.
+fn resolve_hrp(template: &str, remote_domain: Option<u32>) -> anyhow::Result<Hrp> {
+    let hrp = if template.contains("{domain}") {
+        let domain = remote_domain.ok_or_else(|| {
+            anyhow::anyhow!("--hrp {template} contains {{domain}} but --remote-domain was not set")
+        })?;
+        template.replace("{domain}", &domain.to_string())
+    } else {
+        template.to_string()
+    };
+    Hrp::parse(&hrp).map_err(|e| {
+        anyhow::anyhow!(
+            "Invalid bech32 prefix {hrp}, error: {e}. Prefixes are 1-83 ASCII characters from '!' to '~'"
+        )
+    })
+}
+
+/// Parses a bech32 prefix for `--hrp-allowlist`.
+fn parse_hrp(s: &str) -> anyhow::Result<Hrp> {
+    Hrp::parse(s).map_err(|e| anyhow::anyhow!("Invalid bech32 prefix {s}, error: {e}"))
+}
+
+/// Lowercases `hrp`, warning if that changes it. Bech32 prefixes are case-insensitive and always
+/// printed in lowercase, but mixed-case input would otherwise be rejected.
+fn normalize_hrp(hrp: String) -> String {
+    let lowercase = hrp.to_ascii_lowercase();
+    if lowercase != hrp {
+        eprintln!(
+            "Warning: bech32 prefixes are printed in lowercase, so --hrp {hrp} is used as {lowercase}"
+        );
+    }
+    lowercase
 }
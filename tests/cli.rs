@@ -0,0 +1,1204 @@
+//! End-to-end tests of the `sov-warp-utils` binary.
+
+use std::{
+    io::{BufRead, BufReader, Write},
+    path::PathBuf,
+    process::{Command, Output, Stdio},
+};
+
+const DEPLOYER: &str = "0xD2C1bE33A0BcD2007136afD8Ed61CC7561aDa747";
+const TOKEN_ADDRESS: &str = "0x4ed7c70F96B99c776995fB64377f0d4aB3B0e1C1";
+
+/// Runs the binary with `args`, writing `stdin` to its stdin.
+fn run_with_stdin(args: &[&str], stdin: &[u8]) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_sov-warp-utils"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("failed to run sov-warp-utils");
+    // Commands that don't read stdin may exit before it's written.
+    let _ = child.stdin.take().unwrap().write_all(stdin);
+    child.wait_with_output().unwrap()
+}
+
+/// Runs the binary with `args` and an empty stdin.
+fn run(args: &[&str]) -> Output {
+    run_with_stdin(args, b"")
+}
+
+/// Runs the binary with `args`, failing the test unless it succeeds, and returns its stdout.
+fn stdout(args: &[&str]) -> String {
+    let output = run(args);
+    assert!(
+        output.status.success(),
+        "{args:?} failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).unwrap()
+}
+
+/// Runs the binary with `args`, failing the test unless it fails, and returns its stderr.
+fn stderr(args: &[&str]) -> String {
+    let output = run(args);
+    assert!(!output.status.success(), "{args:?} succeeded");
+    String::from_utf8(output.stderr).unwrap()
+}
+
+/// A path in the temporary directory that is unique to this process and `name`, with any file
+/// left there by an earlier run removed.
+fn temp_path(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("sov-warp-utils-{}-{name}", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    path
+}
+
+#[test]
+fn trailing_newlines_are_trimmed() {
+    let expected = stdout(&["hash", TOKEN_ADDRESS]);
+    let output = run_with_stdin(&["hash"], format!("{TOKEN_ADDRESS}\n").as_bytes());
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), expected);
+
+    let receipt = temp_path("trailing-newline.json");
+    std::fs::write(
+        &receipt,
+        format!("{{\"contractAddress\": \"{TOKEN_ADDRESS}\"}}\n\n"),
+    )
+    .unwrap();
+    let from_receipt = stdout(&["-d", DEPLOYER, "--from-receipt", receipt.to_str().unwrap()]);
+    assert_eq!(from_receipt, stdout(&["-d", DEPLOYER, "-t", TOKEN_ADDRESS]));
+}
+
+#[test]
+fn invalid_hrps_are_rejected() {
+    let error = stderr(&["-d", DEPLOYER, "-t", TOKEN_ADDRESS, "--hrp", "to ken"]);
+    assert!(error.contains("Invalid bech32 prefix to ken"), "{error}");
+
+    let output = run(&["-d", DEPLOYER, "-t", TOKEN_ADDRESS, "--hrp", "Token_"]);
+    assert!(output.status.success());
+    let warning = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        warning.contains("--hrp Token_ is used as token_"),
+        "{warning}"
+    );
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        stdout(&["-d", DEPLOYER, "-t", TOKEN_ADDRESS])
+    );
+}
+
+#[test]
+fn no_clobber_refuses_to_overwrite_output() {
+    let path = temp_path("no-clobber.json");
+    let output = path.to_str().unwrap();
+    let args = ["-d", DEPLOYER, "-t", TOKEN_ADDRESS, "--format", "json"];
+    let with_output = [&args[..], &["--output", output, "--no-clobber"]].concat();
+    stdout(&with_output);
+    let written = std::fs::read_to_string(&path).unwrap();
+    assert_eq!(written, stdout(&args));
+
+    let error = stderr(&[&with_output[..], &["--local-decimals", "6"]].concat());
+    assert!(error.contains("Failed to write output"), "{error}");
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), written);
+}
+
+#[test]
+fn group_separator_replaces_the_space() {
+    let args = [
+        "-d",
+        DEPLOYER,
+        "-t",
+        TOKEN_ADDRESS,
+        "--quiet",
+        "--group-bech32",
+    ];
+    let spaced = stdout(&args);
+    let dashed = stdout(&[&args[..], &["--group-separator", "-"]].concat());
+    assert_eq!(dashed, spaced.replace(' ', "-"));
+    assert!(dashed.contains("token_195zg-ht0w-mhcx-"), "{dashed}");
+
+    let error = stderr(&[&args[..], &["--group-separator", "x"]].concat());
+    assert!(error.contains("x is a letter or digit"), "{error}");
+}
+
+#[test]
+fn conformance_checks_every_vector() {
+    let vector = |token_id: &str| {
+        format!(
+            r#"{{"deployer": "{DEPLOYER}", "token_address": "{TOKEN_ADDRESS}", "decimals": 18, "warp_route_id": "0x9c081539d40ef7b02d359c5d694e006f0c1130097466cd22d062e07065c6987a", "token_id": "{token_id}"}}"#
+        )
+    };
+    let good = vector("token_195zght0wmhcx9j462jtj9lypdua4xw07r6jnjfjsddsmzeh2wsfqrhddvf");
+    let path = temp_path("conformance.json");
+    std::fs::write(&path, format!("[{good}, {good}]")).unwrap();
+    assert_eq!(
+        stdout(&["conformance", path.to_str().unwrap()]),
+        "All 2 reference vectors passed\n"
+    );
+
+    let bad = vector("token_1q9438c85uqmal2qfdy6lzxmjnf46pzgmnt87u04fz300pqs28urq8llxdk");
+    std::fs::write(&path, format!("[{good}, {bad}]")).unwrap();
+    let output = run(&["conformance", path.to_str().unwrap()]);
+    assert!(!output.status.success());
+    let report = String::from_utf8(output.stdout).unwrap();
+    assert!(
+        report.starts_with("FAIL vector 1: Token ID mismatch"),
+        "{report}"
+    );
+    assert!(
+        report.contains(&format!("  Token Address: {TOKEN_ADDRESS}")),
+        "{report}"
+    );
+}
+
+#[test]
+fn name_salt_is_appended_to_the_token_name() {
+    let args = ["-d", DEPLOYER, "-t", TOKEN_ADDRESS, "--quiet"];
+    let salted = stdout(&[&args[..], &["--name-salt", "#2"]].concat());
+    assert_eq!(
+        salted,
+        "0x9c081539d40ef7b02d359c5d694e006f0c1130097466cd22d062e07065c6987a\ntoken_1jn64up3yfxd4qvyjvcaxdrtq2za8vcwv66hh7j6kda90rl5ypsfque9u6q\n"
+    );
+    let named = stdout(
+        &[
+            &args[..],
+            &[
+                "--token-name",
+                "Synthetic token for 0x9c081539d40ef7b02d359c5d694e006f0c1130097466cd22d062e07065c6987a#2",
+            ],
+        ]
+        .concat(),
+    );
+    assert_eq!(salted, named);
+    // The salt also applies with --hash-cmd.
+    if cfg!(unix) {
+        let hashed =
+            stdout(&[&args[..], &["--name-salt", "#2", "--hash-cmd", "sha256sum"]].concat());
+        assert_eq!(salted, hashed);
+    }
+}
+
+/// Sends an HTTP/1.0 `POST /compute` with `body` to `port` and returns the response.
+#[cfg(feature = "server")]
+fn post_compute(port: u16, body: &str) -> Option<String> {
+    use std::io::Read;
+
+    let mut stream = std::net::TcpStream::connect(("127.0.0.1", port)).ok()?;
+    let request = format!(
+        "POST /compute HTTP/1.0\r\nContent-Length: {}\r\n\r\n{body}",
+        body.len()
+    );
+    stream.write_all(request.as_bytes()).ok()?;
+    let mut response = String::new();
+    stream.read_to_string(&mut response).ok()?;
+    Some(response)
+}
+
+#[cfg(feature = "server")]
+#[test]
+fn server_listens_locally_and_caps_the_body() {
+    // Reserve a free port, then release it for the server.
+    let port = std::net::TcpListener::bind(("127.0.0.1", 0))
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    let mut server = Command::new(env!("CARGO_BIN_EXE_sov-warp-utils"))
+        .args(["serve", "--port", &port.to_string()])
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    let body = format!(
+        r#"{{"deployer": "{DEPLOYER}", "token_address": "{TOKEN_ADDRESS}", "decimals": 18}}"#
+    );
+    let response = (0..50).find_map(|_| {
+        std::thread::sleep(std::time::Duration::from_millis(100));
+        post_compute(port, &body)
+    });
+    let oversized = post_compute(port, &" ".repeat(64 * 1024));
+    server.kill().unwrap();
+    server.wait().unwrap();
+
+    let response = response.expect("the server never answered");
+    assert!(response.starts_with("HTTP/1.0 200"), "{response}");
+    assert!(
+        response.contains("token_195zght0wmhcx9j462jtj9lypdua4xw07r6jnjfjsddsmzeh2wsfqrhddvf"),
+        "{response}"
+    );
+    let oversized = oversized.unwrap();
+    assert!(oversized.starts_with("HTTP/1.0 413"), "{oversized}");
+}
+
+#[test]
+fn expected_ids_are_compared_as_bytes() {
+    let token_id_hex = "0x2D048BADEEDDF062caba549722fc816f3b5339fe1ea53926506B61B166EA7412";
+    let warp_route_id = "0x9C081539d40ef7b02d359c5d694e006f0c1130097466cd22d062e07065c6987A";
+    let output = stdout(&[
+        "verify",
+        "-d",
+        DEPLOYER,
+        "-t",
+        TOKEN_ADDRESS,
+        "--warp-route-id",
+        warp_route_id,
+        "--token-id",
+        token_id_hex,
+    ]);
+    assert_eq!(output, "OK: the expected IDs match\n");
+
+    let token_id = "TOKEN_195ZGHT0WMHCX9J462JTJ9LYPDUA4XW07R6JNJFJSDDSMZEH2WSFQRHDDVF";
+    stdout(&[
+        "-d",
+        DEPLOYER,
+        "-t",
+        TOKEN_ADDRESS,
+        "--assert-token-id",
+        token_id,
+    ]);
+
+    let wrong = token_id_hex.replace("7412", "7413");
+    let error = stderr(&[
+        "verify",
+        "-d",
+        DEPLOYER,
+        "-t",
+        TOKEN_ADDRESS,
+        "--token-id",
+        &wrong,
+    ]);
+    assert!(error.contains("The computed IDs don't match"), "{error}");
+}
+
+#[test]
+fn explain_json_lists_every_step() {
+    let output = stdout(&["-d", DEPLOYER, "-t", TOKEN_ADDRESS, "--explain-json"]);
+    let steps: Vec<serde_json::Value> = serde_json::from_str(&output).unwrap();
+    let names: Vec<_> = steps
+        .iter()
+        .map(|step| step["step"].as_str().unwrap())
+        .collect();
+    assert_eq!(
+        names,
+        [
+            "padded_token_address",
+            "separator",
+            "deployer",
+            "warp_route_id",
+            "token_name",
+            "decimals",
+            "token_hash",
+            "token_id",
+        ]
+    );
+    assert_eq!(
+        steps[6]["bytes"],
+        "0x2d048badeeddf062caba549722fc816f3b5339fe1ea53926506b61b166ea741c"
+    );
+    assert_eq!(
+        steps[7]["bytes"],
+        "0x2d048badeeddf062caba549722fc816f3b5339fe1ea53926506b61b166ea7412"
+    );
+}
+
+#[test]
+fn empty_batches_print_well_formed_output() {
+    let batch = |extra: &[&str]| {
+        let output = run(&[&["-d", DEPLOYER, "--batch", "-"], extra].concat());
+        assert!(output.status.success(), "{extra:?} failed");
+        let summary = String::from_utf8(output.stderr).unwrap();
+        assert_eq!(summary, "Computed 0 entries, 0 failed\n");
+        String::from_utf8(output.stdout).unwrap()
+    };
+    for format in ["text", "json", "yaml", "sql"] {
+        assert_eq!(batch(&["--format", format]), "", "{format}");
+    }
+    assert_eq!(
+        batch(&["--format", "csv"]),
+        "deployer,token_address,decimals,warp_route_id,token_name,token_id\n"
+    );
+    assert_eq!(
+        batch(&["--format", "markdown"]),
+        "| Token Address | Decimals | Warp Route ID | Token ID |\n| --- | --: | --- | --- |\n"
+    );
+    let array: Vec<serde_json::Value> = serde_json::from_str(&batch(&["--json-array"])).unwrap();
+    assert!(array.is_empty());
+}
+
+#[test]
+fn native_hashes_the_zero_address() {
+    let native = stdout(&[
+        "-d",
+        DEPLOYER,
+        "--native",
+        "--local-decimals",
+        "0",
+        "--quiet",
+    ]);
+    assert_eq!(
+        native,
+        "0x56a81936a08fa319c5be26d61186458d567ffbd8599e755deebc9b3abdfb0956\ntoken_1ysltwvz58zqyq6hfve3eaz8e23t79slyz407jkl3kyeq9jvqggqql488gq\n"
+    );
+    let zero = "0x0000000000000000000000000000000000000000";
+    let explicit = stdout(&[
+        "-d",
+        DEPLOYER,
+        "-t",
+        zero,
+        "--local-decimals",
+        "0",
+        "--quiet",
+    ]);
+    assert_eq!(native, explicit);
+}
+
+#[test]
+fn bech32_data_only_is_the_suffix_of_the_token_id() {
+    let args = ["-d", DEPLOYER, "-t", TOKEN_ADDRESS, "--format", "json"];
+    let full: serde_json::Value = serde_json::from_str(&stdout(&args)).unwrap();
+    let data_only = stdout(&[&args[..], &["--bech32-data-only"]].concat());
+    let data_only: serde_json::Value = serde_json::from_str(&data_only).unwrap();
+    let (full, data) = (
+        full["token_id"].as_str().unwrap(),
+        data_only["token_id"].as_str().unwrap(),
+    );
+    assert_eq!(
+        data,
+        "95zght0wmhcx9j462jtj9lypdua4xw07r6jnjfjsddsmzeh2wsfqrhddvf"
+    );
+    assert_eq!(full, format!("token_1{data}"));
+}
+
+#[test]
+fn closed_stdout_is_not_an_error() {
+    let status = |args: &[&str], stdin: Vec<u8>, read_first_line: bool| {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_sov-warp-utils"))
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let mut input = child.stdin.take().unwrap();
+        let writer = std::thread::spawn(move || {
+            let _ = input.write_all(&stdin);
+        });
+        let stdout = child.stdout.take().unwrap();
+        if read_first_line {
+            let mut line = String::new();
+            BufReader::new(stdout).read_line(&mut line).unwrap();
+            assert!(!line.is_empty());
+        } else {
+            drop(stdout);
+        }
+        let output = child.wait_with_output().unwrap();
+        writer.join().unwrap();
+        let stderr = String::from_utf8(output.stderr).unwrap();
+        assert!(!stderr.contains("panicked"), "{stderr}");
+        assert!(
+            output.status.success() || output.status.code() == Some(141),
+            "{:?}: {stderr}",
+            output.status
+        );
+    };
+    let sweep = [
+        "sweep-decimals",
+        "-d",
+        DEPLOYER,
+        "-t",
+        TOKEN_ADDRESS,
+        "--from",
+        "0",
+        "--to",
+        "255",
+    ];
+    status(&sweep, Vec::new(), false);
+    status(&["selftest"], Vec::new(), false);
+    status(&["man"], Vec::new(), false);
+    status(&["man"], Vec::new(), true);
+    let batch: String = (1..5000).map(|i| format!("0x{i:040x}\n")).collect();
+    for format in ["text", "csv", "json"] {
+        let args = ["-d", DEPLOYER, "--batch", "-", "--format", format];
+        status(&args, batch.clone().into_bytes(), true);
+    }
+}
+
+#[test]
+fn bech32_values_outside_the_allowlist_are_rejected() {
+    let hrp = bech32::Hrp::parse("cosmos").unwrap();
+    let cosmos = bech32::encode::<bech32::Bech32>(hrp, &[0x11; 20]).unwrap();
+    for args in [vec!["decode", &cosmos], vec![&cosmos]] {
+        assert!(
+            stderr(&args).contains(
+                "Rejected bech32 value with prefix cosmos, which is not in --hrp-allowlist token_"
+            ),
+            "{args:?}"
+        );
+    }
+    let allowed = stdout(&["decode", &cosmos, "--hrp-allowlist", "token_,cosmos"]);
+    assert_eq!(
+        allowed,
+        format!("Type: Bech32 value\nBytes: 0x{}\n", "11".repeat(20))
+    );
+    let token_id = "token_195zght0wmhcx9j462jtj9lypdua4xw07r6jnjfjsddsmzeh2wsfqrhddvf";
+    assert!(stdout(&["decode", token_id]).starts_with("Type: Token ID\n"));
+}
+
+#[test]
+fn repeated_runs_print_identical_output() {
+    let batch: String = (1..200)
+        .map(|i| format!("0x{i:040x},{}\n", i % 19))
+        .chain([format!("{TOKEN_ADDRESS}\n"), format!("{TOKEN_ADDRESS},6\n")])
+        .collect();
+    let single = ["-d", DEPLOYER, "-t", TOKEN_ADDRESS];
+    let batched = ["-d", DEPLOYER, "--batch", "-"];
+    let mut runs = vec![([&batched[..], &["--json-array"]].concat(), &batch[..])];
+    for format in ["text", "json"] {
+        runs.push(([&single[..], &["--format", format]].concat(), ""));
+        runs.push(([&batched[..], &["--format", format]].concat(), &batch[..]));
+        runs.push((
+            [&batched[..], &["--format", format, "--sort-output"]].concat(),
+            &batch[..],
+        ));
+    }
+    for (args, stdin) in runs {
+        let first = run_with_stdin(&args, stdin.as_bytes());
+        let second = run_with_stdin(&args, stdin.as_bytes());
+        assert!(first.status.success(), "{args:?}");
+        assert!(!first.stdout.is_empty(), "{args:?}");
+        assert_eq!(first.stdout, second.stdout, "{args:?}");
+    }
+}
+
+#[test]
+fn normalize_canonicalizes_every_kind_of_identifier() {
+    let token_id = "token_195zght0wmhcx9j462jtj9lypdua4xw07r6jnjfjsddsmzeh2wsfqrhddvf";
+    let hash = "0x9c081539d40ef7b02d359c5d694e006f0c1130097466cd22d062e07065c6987a";
+    let grouped = stdout(&["-d", DEPLOYER, "-t", TOKEN_ADDRESS, "--group-bech32"]);
+    let grouped = grouped
+        .lines()
+        .find_map(|line| line.strip_prefix("Token ID: "))
+        .unwrap();
+    assert!(grouped.contains(' '));
+    let cases = [
+        (TOKEN_ADDRESS, "0x4ed7c70f96b99c776995fb64377f0d4ab3b0e1c1"),
+        (&hash.to_uppercase()[2..], hash),
+        (&hash.replacen("0x", "0X", 1).to_uppercase(), hash),
+        (hash, hash),
+        (grouped, token_id),
+        (&token_id.to_uppercase(), token_id),
+        (token_id, token_id),
+    ];
+    for (input, expected) in cases {
+        assert_eq!(
+            stdout(&["normalize", input]),
+            format!("{expected}\n"),
+            "{input}"
+        );
+    }
+    let stdin: String = cases
+        .iter()
+        .map(|(input, _)| format!("{input}\n\n"))
+        .collect();
+    let output = run_with_stdin(&["normalize"], stdin.as_bytes());
+    assert!(output.status.success());
+    let expected: String = cases
+        .iter()
+        .map(|(_, canonical)| format!("{canonical}\n"))
+        .collect();
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), expected);
+    assert!(stderr(&["normalize", "0xabcd"]).contains("Failed to normalize 0xabcd"));
+
+    // Values grouped with another separator normalize with the same --group-separator, which is
+    // only removed from a token ID's data, never its prefix.
+    for separator in ["-", "_"] {
+        let grouped = stdout(&[
+            "-d",
+            DEPLOYER,
+            "-t",
+            TOKEN_ADDRESS,
+            "--group-bech32",
+            "--group-separator",
+            separator,
+            "--quiet",
+        ]);
+        let grouped = grouped.lines().nth(1).unwrap();
+        assert!(grouped.contains(separator), "{grouped}");
+        let args = ["normalize", grouped, "--group-separator", separator];
+        assert_eq!(stdout(&args), format!("{token_id}\n"), "{grouped}");
+        assert!(!run(&["normalize", grouped]).status.success());
+    }
+    let dashed_hash = format!(
+        "0x{}",
+        hash[2..]
+            .as_bytes()
+            .chunks(8)
+            .map(|c| std::str::from_utf8(c).unwrap())
+            .collect::<Vec<_>>()
+            .join("-")
+    );
+    assert_eq!(
+        stdout(&["normalize", &dashed_hash, "--group-separator", "-"]),
+        format!("{hash}\n")
+    );
+}
+
+#[test]
+fn route_types_decide_the_hashed_address() {
+    let native = stdout(&["-d", DEPLOYER, "--native", "--quiet"]);
+    assert_eq!(
+        stdout(&["-d", DEPLOYER, "--route-type", "native", "--quiet"]),
+        native
+    );
+    assert_eq!(
+        stdout(&[
+            "-d",
+            DEPLOYER,
+            "-t",
+            TOKEN_ADDRESS,
+            "--route-type",
+            "collateral"
+        ]),
+        stdout(&["-d", DEPLOYER, "-t", TOKEN_ADDRESS])
+    );
+    let missing = stderr(&["-d", DEPLOYER, "--route-type", "collateral"]);
+    assert!(missing.contains("--token-address"), "{missing}");
+    let native_with_address = stderr(&[
+        "-d",
+        DEPLOYER,
+        "-t",
+        TOKEN_ADDRESS,
+        "--route-type",
+        "native",
+    ]);
+    assert!(native_with_address.contains("drop --token-address"));
+    let synthetic = stderr(&["-d", DEPLOYER, "--route-type", "synthetic"]);
+    assert!(
+        synthetic.contains("invalid value 'synthetic'"),
+        "{synthetic}"
+    );
+    let config = temp_path("route-type-config.yaml");
+    std::fs::write(&config, format!("token-address: \"{TOKEN_ADDRESS}\"\n")).unwrap();
+    let config = config.to_str().unwrap();
+    for flag in ["--hyperlane-config", "--config"] {
+        for route_type in ["--native", "--route-type=native"] {
+            let conflict = stderr(&["-d", DEPLOYER, route_type, flag, config]);
+            assert!(conflict.contains("cannot be used with"), "{conflict}");
+        }
+    }
+}
+
+#[test]
+fn structured_output_includes_the_hex_token_id() {
+    let token_id_hex = "0x2d048badeeddf062caba549722fc816f3b5339fe1ea53926506b61b166ea7412";
+    let args = ["-d", DEPLOYER, "-t", TOKEN_ADDRESS];
+    let json: serde_json::Value =
+        serde_json::from_str(&stdout(&[&args[..], &["--format", "json"]].concat())).unwrap();
+    assert_eq!(json["token_id_hex"], token_id_hex);
+    let yaml = stdout(&[&args[..], &["--format", "yaml"]].concat());
+    assert!(
+        yaml.contains(&format!("token_id_hex: {token_id_hex}\n")),
+        "{yaml}"
+    );
+    if cfg!(unix) {
+        let hashed =
+            stdout(&[&args[..], &["--format", "json", "--hash-cmd", "sha256sum"]].concat());
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&hashed).unwrap(),
+            json
+        );
+    }
+
+    let hex_line = format!("Token ID (hex): {token_id_hex}\n");
+    assert!(!stdout(&args).contains("Token ID (hex)"));
+    assert!(stdout(&[&args[..], &["--show-token-id-hex"]].concat()).ends_with(&hex_line));
+    let csv = stdout(&[&args[..], &["--format", "csv"]].concat());
+    assert!(!csv.contains("token_id_hex"));
+    let csv = stdout(&[&args[..], &["--format", "csv", "--show-token-id-hex"]].concat());
+    let (header, line) = csv.trim_end().split_once('\n').unwrap();
+    assert!(header.ends_with(",token_id_hex"));
+    assert!(line.ends_with(&format!(",{token_id_hex}")));
+
+    let manifest = temp_path("hex-manifest.json");
+    stdout(&[&args[..], &["--manifest", manifest.to_str().unwrap()]].concat());
+    let written: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&manifest).unwrap()).unwrap();
+    assert_eq!(written["entries"][0]["token_id_hex"], token_id_hex);
+    std::fs::remove_file(manifest).unwrap();
+}
+
+#[test]
+fn derive_batch_reads_quoted_csv_fields() {
+    let name = "Wrapped, \"quoted\" token";
+    let csv = format!(
+        "deployer, token_address ,\"token_name\",decimals\n{DEPLOYER},{TOKEN_ADDRESS}, \"Wrapped, \"\"quoted\"\" token\" ,6\n"
+    );
+    let args = [
+        "derive-batch",
+        "-",
+        "--input-format",
+        "csv",
+        "--format",
+        "json",
+    ];
+    let output = run_with_stdin(&args, csv.as_bytes());
+    assert!(output.status.success());
+    let rows: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let single = stdout(&[
+        "-d",
+        DEPLOYER,
+        "-t",
+        TOKEN_ADDRESS,
+        "--local-decimals",
+        "6",
+        "--token-name",
+        name,
+        "--format",
+        "json",
+    ]);
+    let single: serde_json::Value = serde_json::from_str(&single).unwrap();
+    assert_eq!(rows[0], single);
+
+    // The CSV output, which quotes the token name, reads back as the same rows.
+    let csv_args = ["derive-batch", "-", "--input-format", "csv"];
+    let written = run_with_stdin(&csv_args, csv.as_bytes()).stdout;
+    assert!(String::from_utf8_lossy(&written).contains("\"Wrapped, \"\"quoted\"\" token\""));
+    assert_eq!(run_with_stdin(&csv_args, &written).stdout, written);
+}
+
+#[test]
+fn derive_batch_reports_each_bad_row() {
+    let csv = [
+        "deployer,token_address,decimals,token_name".to_string(),
+        format!("{DEPLOYER},{TOKEN_ADDRESS},18,"),
+        format!("0xzz,{TOKEN_ADDRESS},18,"),
+        format!("{DEPLOYER},{TOKEN_ADDRESS},18"),
+        format!("{DEPLOYER},,18,"),
+        format!("{DEPLOYER},{TOKEN_ADDRESS},256,"),
+        format!("{DEPLOYER},{TOKEN_ADDRESS},18,\"unterminated"),
+        format!("{DEPLOYER},{TOKEN_ADDRESS},18,\"name\"x"),
+    ]
+    .join("\n");
+    let output = run_with_stdin(&["derive-batch", "-"], csv.as_bytes());
+    assert!(!output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(stdout.lines().count(), 2, "{stdout}");
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    for expected in [
+        "Line 3: Invalid deployer 0xzz",
+        "Line 4: Expected 4 fields, got 3",
+        "Line 5: Missing token_address",
+        "Line 6: Invalid decimals 256",
+        "Line 7: Unterminated quote in field 4",
+        "Line 8: Unexpected 'x' after the closing quote of field 4",
+        "Computed 1 entries, 6 failed",
+        "Failed to parse 6 of 7 batch rows",
+    ] {
+        assert!(stderr.contains(expected), "{expected}: {stderr}");
+    }
+
+    let json = format!(
+        "[{{\"deployer\": \"{DEPLOYER}\", \"token_address\": \"{TOKEN_ADDRESS}\"}}, {{\"deployer\": \"{DEPLOYER}\"}}]"
+    );
+    let output = run_with_stdin(
+        &["derive-batch", "-", "--input-format", "json"],
+        json.as_bytes(),
+    );
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("Row 2: missing field `token_address`"),
+        "{stderr}"
+    );
+    assert!(
+        stderr.contains("Failed to parse 1 of 2 batch rows"),
+        "{stderr}"
+    );
+}
+
+#[test]
+fn derive_batch_accepts_empty_files() {
+    for format in ["csv", "json"] {
+        let output = run_with_stdin(&["derive-batch", "-", "--input-format", format], b"\n");
+        assert!(output.status.success(), "{format}");
+        let stderr = String::from_utf8(output.stderr).unwrap();
+        assert!(
+            stderr.contains("Computed 0 entries, 0 failed"),
+            "{format}: {stderr}"
+        );
+    }
+}
+
+#[test]
+fn derive_batch_takes_the_batch_output_flags() {
+    let csv = format!(
+        "deployer,token_address\n{DEPLOYER},{TOKEN_ADDRESS}\n{DEPLOYER},0x0000000000000000000000000000000000000001\n"
+    );
+    let batch = |extra: &[&str]| {
+        run_with_stdin(
+            &[&["derive-batch", "-", "--input-format", "csv"], extra].concat(),
+            csv.as_bytes(),
+        )
+    };
+    let output = batch(&["--hrp", "Route_", "--format", "json"]);
+    assert!(output.status.success());
+    let rows: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert!(rows[0]["token_id"].as_str().unwrap().starts_with("route_1"));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--hrp Route_ is used as route_"));
+
+    let output = batch(&["--max-batch-size", "1"]);
+    assert!(!output.status.success());
+    assert!(output.stdout.is_empty());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("Batch has 2 entries, more than --max-batch-size 1"),
+        "{stderr}"
+    );
+    assert!(batch(&["--max-batch-size", "2"]).status.success());
+
+    let path = temp_path("derive-batch.csv");
+    let path_arg = path.to_str().unwrap();
+    assert!(
+        batch(&["--output", path_arg, "--no-clobber"])
+            .status
+            .success()
+    );
+    let written = std::fs::read_to_string(&path).unwrap();
+    let output = batch(&["--output", path_arg, "--no-clobber", "--format", "json"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Failed to write output"));
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), written);
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn decimal_inputs_derive_the_same_ids_as_hex() {
+    let deployer = "1203208674598707348246046875766576982950425765703";
+    let token_address = "450113280991427438772068108049397855241037210049";
+    let hex = stdout(&["-d", DEPLOYER, "-t", TOKEN_ADDRESS, "--quiet"]);
+    for (format, deployer, token_address) in [
+        ("decimal", deployer, token_address),
+        ("decimal", &format!("0d{deployer}"), TOKEN_ADDRESS),
+        ("auto", deployer, &format!("0d{token_address}")),
+        ("auto", DEPLOYER, token_address),
+    ] {
+        let args = [
+            "-d",
+            deployer,
+            "-t",
+            token_address,
+            "--input-format",
+            format,
+            "--quiet",
+        ];
+        assert_eq!(stdout(&args), hex, "{args:?}");
+    }
+    // 40 digits are hex in `auto` mode, but decimal in `decimal` mode.
+    let digits = "1234567890123456789012345678901234567890";
+    let auto = stdout(&[
+        "-d",
+        digits,
+        "-t",
+        TOKEN_ADDRESS,
+        "--input-format",
+        "auto",
+        "--quiet",
+    ]);
+    let bare_hex = stdout(&["-d", digits, "-t", TOKEN_ADDRESS, "--quiet"]);
+    assert_eq!(auto, bare_hex);
+    let too_large = "1461501637330902918203684832716283019655932542976";
+    let error = stderr(&[
+        "-d",
+        too_large,
+        "-t",
+        TOKEN_ADDRESS,
+        "--input-format",
+        "decimal",
+    ]);
+    assert!(error.contains("doesn't fit in 20 bytes"), "{error}");
+}
+
+#[test]
+fn skip_and_take_select_a_slice_of_the_batch() {
+    let tokens = [
+        "0x0000000000000000000000000000000000000001",
+        TOKEN_ADDRESS,
+        "0x0000000000000000000000000000000000000003",
+    ];
+    let batch: String = tokens.iter().map(|token| format!("{token},6\n")).collect();
+    let args = [
+        "-d", DEPLOYER, "--batch", "-", "--format", "json", "--skip", "1", "--take", "1",
+    ];
+    let output = run_with_stdin(&args, batch.as_bytes());
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let rows: Vec<serde_json::Value> = stdout
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(rows.len(), 1, "{stdout}");
+    assert_eq!(rows[0]["token_address"], TOKEN_ADDRESS.to_lowercase());
+    assert_eq!(rows[0]["decimals"], 6);
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("Computed 1 entries, 0 failed"), "{stderr}");
+
+    // Skipping past the end computes nothing.
+    let output = run_with_stdin(
+        &["-d", DEPLOYER, "--batch", "-", "--skip", "5"],
+        batch.as_bytes(),
+    );
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Computed 0 entries, 0 failed"));
+}
+
+#[test]
+fn piped_output_has_no_ansi_escapes() {
+    let runs: [&[&str]; 5] = [
+        &["-d", DEPLOYER, "-t", TOKEN_ADDRESS, "--show-token-id-hex"],
+        &["-d", DEPLOYER, "-t", TOKEN_ADDRESS, "--explain"],
+        &[
+            "-d",
+            DEPLOYER,
+            "-t",
+            TOKEN_ADDRESS,
+            "--assert-token-id",
+            DEPLOYER,
+        ],
+        &["-d", "0xzz", "-t", TOKEN_ADDRESS],
+        &["--help"],
+    ];
+    for args in runs {
+        for no_color in [false, true] {
+            let mut command = Command::new(env!("CARGO_BIN_EXE_sov-warp-utils"));
+            command.args(args).stdin(Stdio::null());
+            if no_color {
+                command.env("NO_COLOR", "1");
+            }
+            let output = command.output().unwrap();
+            assert!(!output.stdout.is_empty() || !output.stderr.is_empty());
+            for stream in [&output.stdout, &output.stderr] {
+                let text = String::from_utf8_lossy(stream);
+                assert!(!text.contains("\x1b["), "{args:?}: {text}");
+            }
+        }
+    }
+}
+
+#[test]
+fn mismatches_explain_which_input_likely_differs() {
+    let warp_route_id = "0x9c081539d40ef7b02d359c5d694e006f0c1130097466cd22d062e07065c6987a";
+    let token_id = "token_195zght0wmhcx9j462jtj9lypdua4xw07r6jnjfjsddsmzeh2wsfqrhddvf";
+    let assert = |args: &[&str]| {
+        let base = [
+            "--assert-warp-route-id",
+            warp_route_id,
+            "--assert-token-id",
+            token_id,
+        ];
+        stderr(&[args, &base].concat())
+    };
+
+    // Swapping the deployer and token address changes the warp route ID.
+    let swapped = assert(&["-d", TOKEN_ADDRESS, "-t", DEPLOYER]);
+    assert!(swapped.contains(&format!("Expected Warp Route ID: {warp_route_id}")));
+    assert!(swapped.contains("Computed Warp Route ID: 0x"));
+    assert!(
+        swapped.contains(
+            "The warp route IDs differ, so the deployer or token address (or the derivation scheme) likely differs"
+        ),
+        "{swapped}"
+    );
+
+    let decimals = assert(&["-d", DEPLOYER, "-t", TOKEN_ADDRESS, "--local-decimals", "6"]);
+    assert!(decimals.contains(&format!("Expected Token ID: {token_id} (0x")));
+    assert!(
+        decimals.contains(
+            "The expected token ID ends in 18 but 6 decimals were used, so the decimals likely differ"
+        ),
+        "{decimals}"
+    );
+
+    let named = assert(&["-d", DEPLOYER, "-t", TOKEN_ADDRESS, "--token-name", "Other"]);
+    assert!(
+        named.contains(
+            "The warp route IDs match but the token IDs differ, so the token name likely differs"
+        ),
+        "{named}"
+    );
+
+    let token_only = stderr(&[
+        "-d",
+        DEPLOYER,
+        "-t",
+        TOKEN_ADDRESS,
+        "--token-name",
+        "Other",
+        "--assert-token-id",
+        token_id,
+    ]);
+    assert!(!token_only.contains("Expected Warp Route ID"));
+    assert!(
+        token_only
+            .contains("The token IDs differ but the decimals match. Pass --assert-warp-route-id"),
+        "{token_only}"
+    );
+    for error in [swapped, decimals, named, token_only] {
+        assert!(error.contains("The computed IDs don't match the expected ones"));
+    }
+}
+
+#[test]
+fn update_rewrites_changed_manifest_entries_in_place() {
+    let path = temp_path("update-manifest.json");
+    let path_arg = path.to_str().unwrap();
+    let batch = format!("{TOKEN_ADDRESS},18\n0x0000000000000000000000000000000000000001,6\n");
+    let output = run_with_stdin(
+        &[
+            "-d",
+            DEPLOYER,
+            "--batch",
+            "-",
+            "--manifest",
+            path_arg,
+            "--quiet",
+        ],
+        batch.as_bytes(),
+    );
+    assert!(output.status.success());
+    let mut manifest: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+    let untouched = manifest["entries"][1].clone();
+    manifest["entries"][0]["decimals"] = 6.into();
+    manifest["entries"][0]["note"] = "bridged USDC".into();
+    std::fs::write(&path, serde_json::to_string_pretty(&manifest).unwrap()).unwrap();
+
+    let output = run(&["--update", path_arg]);
+    assert!(output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("Updated entry 0: token_195zght0wmhcx9j462jtj9lypdua4xw07r6jnjfjsddsmzeh2wsfqrhddvf -> token_1"), "{stderr}");
+    assert!(stderr.contains("Updated 1 of 2 entries"), "{stderr}");
+
+    let updated: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+    let entry = &updated["entries"][0];
+    let expected = stdout(&[
+        "-d",
+        DEPLOYER,
+        "-t",
+        TOKEN_ADDRESS,
+        "--local-decimals",
+        "6",
+        "--format",
+        "json",
+    ]);
+    let expected: serde_json::Value = serde_json::from_str(&expected).unwrap();
+    for field in [
+        "warp_route_id",
+        "token_name",
+        "token_id",
+        "token_id_hex",
+        "decimals",
+    ] {
+        assert_eq!(entry[field], expected[field], "{field}");
+    }
+    assert_eq!(entry["note"], "bridged USDC");
+    assert_eq!(updated["entries"][1], untouched);
+    assert_eq!(updated["tool"], "sov-warp-utils");
+
+    // A second update finds nothing to change and leaves the file alone.
+    let before = std::fs::read_to_string(&path).unwrap();
+    let output = run(&["--update", path_arg]);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Updated 0 of 2 entries"));
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), before);
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn later_configs_override_earlier_ones_and_flags_override_both() {
+    let other = "0x0000000000000000000000000000000000000001";
+    let base = temp_path("base-config.json");
+    let token = temp_path("token-config.yaml");
+    std::fs::write(
+        &base,
+        format!("{{\"deployer\": \"{other}\", \"token-address\": \"{other}\"}}"),
+    )
+    .unwrap();
+    std::fs::write(&token, format!("deployer: \"{DEPLOYER}\"\n")).unwrap();
+    let (base_arg, token_arg) = (base.to_str().unwrap(), token.to_str().unwrap());
+    let quiet = |args: &[&str]| stdout(&[args, &["--quiet"]].concat());
+
+    // The token file's deployer wins over the base's; the base's token address is kept.
+    assert_eq!(
+        quiet(&["--config", base_arg, "--config", token_arg]),
+        quiet(&["-d", DEPLOYER, "-t", other])
+    );
+    // In the other order, the base file overrides the token file's deployer.
+    assert_eq!(
+        quiet(&["--config", token_arg, "--config", base_arg]),
+        quiet(&["-d", other, "-t", other])
+    );
+    // Flags win over every file.
+    assert_eq!(
+        quiet(&[
+            "--config",
+            base_arg,
+            "--config",
+            token_arg,
+            "-t",
+            TOKEN_ADDRESS
+        ]),
+        quiet(&["-d", DEPLOYER, "-t", TOKEN_ADDRESS])
+    );
+    assert_eq!(
+        quiet(&["--config", base_arg, "--config", token_arg, "-d", other]),
+        quiet(&["-d", other, "-t", other])
+    );
+    std::fs::remove_file(base).unwrap();
+    std::fs::remove_file(token).unwrap();
+}
+
+#[test]
+fn compare_reports_added_removed_and_changed_tokens() {
+    let (one, two, three) = (
+        "0x0000000000000000000000000000000000000001",
+        "0x0000000000000000000000000000000000000002",
+        "0x0000000000000000000000000000000000000003",
+    );
+    let old = temp_path("compare-old.csv");
+    let new = temp_path("compare-new.csv");
+    std::fs::write(&old, format!("{one},18\n{two},18\n{TOKEN_ADDRESS}\n")).unwrap();
+    std::fs::write(&new, format!("{TOKEN_ADDRESS},18\n{two},6\n{three}\n")).unwrap();
+    let (old_arg, new_arg) = (old.to_str().unwrap(), new.to_str().unwrap());
+    let token_id = |token: &str, decimals: &str| {
+        let output = stdout(&[
+            "-d",
+            DEPLOYER,
+            "-t",
+            token,
+            "--local-decimals",
+            decimals,
+            "--quiet",
+        ]);
+        output.lines().nth(1).unwrap().to_string()
+    };
+
+    let output = run(&["compare", old_arg, new_arg, "-d", DEPLOYER]);
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        format!(
+            "change,token_address,old_token_id,new_token_id\nremoved,{one},{},\nchanged,{two},{},{}\nadded,{three},,{}\n",
+            token_id(one, "18"),
+            token_id(two, "18"),
+            token_id(two, "6"),
+            token_id(three, "18"),
+        )
+    );
+    assert!(String::from_utf8_lossy(&output.stderr).contains("3 tokens differ"));
+
+    // Identical inputs print only the header and still succeed.
+    let output = run(&["compare", old_arg, old_arg, "-d", DEPLOYER]);
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "change,token_address,old_token_id,new_token_id\n"
+    );
+    assert!(String::from_utf8_lossy(&output.stderr).contains("0 tokens differ"));
+
+    // A new deployer changes every token ID.
+    let changed = stdout(&[
+        "compare",
+        old_arg,
+        old_arg,
+        "-d",
+        DEPLOYER,
+        "--new-deployer",
+        one,
+    ]);
+    assert_eq!(
+        changed
+            .lines()
+            .filter(|line| line.starts_with("changed,"))
+            .count(),
+        3
+    );
+
+    std::fs::write(&new, format!("{one}\n{one},6\n")).unwrap();
+    let error = stderr(&["compare", old_arg, new_arg, "-d", DEPLOYER]);
+    assert!(error.contains("appears more than once"), "{error}");
+    let missing = temp_path("compare-missing.csv");
+    stderr(&[
+        "compare",
+        old_arg,
+        missing.to_str().unwrap(),
+        "-d",
+        DEPLOYER,
+    ]);
+    std::fs::remove_file(old).unwrap();
+    std::fs::remove_file(new).unwrap();
+}
+
+#[test]
+fn quiet_batches_keep_the_csv_header() {
+    let batch = format!("{TOKEN_ADDRESS}\n{DEPLOYER}\n");
+    let output = run_with_stdin(
+        &["-d", DEPLOYER, "--batch", "-", "--quiet"],
+        batch.as_bytes(),
+    );
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines.len(), 3, "{stdout}");
+    assert_eq!(
+        lines[0],
+        "deployer,token_address,decimals,warp_route_id,token_name,token_id"
+    );
+    // The deployer as its own token would warn without --quiet; neither it nor the summary is
+    // printed.
+    assert_eq!(String::from_utf8(output.stderr).unwrap(), "");
+
+    let text = run_with_stdin(
+        &[
+            "-d", DEPLOYER, "--batch", "-", "--quiet", "--format", "text",
+        ],
+        batch.as_bytes(),
+    );
+    let text = String::from_utf8(text.stdout).unwrap();
+    assert_eq!(text.lines().count(), 4, "{text}");
+    assert!(text.starts_with("0x9c081539d40ef7b02d359c5d694e006f0c1130097466cd22d062e07065c6987a\ntoken_195zght0wmhcx9j462jtj9lypdua4xw07r6jnjfjsddsmzeh2wsfqrhddvf\n"));
+}
+
+#[test]
+fn hyperlane_configs_derive_every_evm_token() {
+    let fixture = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/fixtures/warp-route.yaml"
+    );
+    let output = run(&["-d", DEPLOYER, "--hyperlane-config", fixture]);
+    assert!(output.status.success());
+    let rows: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let rows = rows.as_array().unwrap();
+    assert_eq!(rows.len(), 2, "{rows:?}");
+    let single = |args: &[&str]| -> serde_json::Value {
+        let output = stdout(&[&["-d", DEPLOYER, "--format", "json"], args].concat());
+        serde_json::from_str(&output).unwrap()
+    };
+    let collateral = single(&["-t", TOKEN_ADDRESS, "--local-decimals", "6"]);
+    let native = single(&["--native"]);
+    for field in ["token_address", "decimals", "warp_route_id", "token_id"] {
+        assert_eq!(rows[0][field], collateral[field], "{field}");
+        assert_eq!(rows[1][field], native[field], "{field}");
+    }
+    assert_eq!(rows[0]["decimals"], 6);
+    assert_eq!(
+        rows[1]["warp_route_id"],
+        "0x56a81936a08fa319c5be26d61186458d567ffbd8599e755deebc9b3abdfb0956"
+    );
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains(
+            "Warning: skipping the EvmHypSynthetic token on optimism, which wraps no EVM token"
+        ),
+        "{stderr}"
+    );
+}
@@ -0,0 +1,39 @@
+//! Checks the preimage builders exposed by the `test-internals` feature byte by byte.
+
+use sha2::{Digest, Sha256};
+use sov_warp_utils::{__private, Address, HexHash, HexString, get_token_name};
+
+const DEPLOYER: &str = "0xD2C1bE33A0BcD2007136afD8Ed61CC7561aDa747";
+const TOKEN_ADDRESS: &str = "0x4ed7c70F96B99c776995fB64377f0d4aB3B0e1C1";
+const WARP_ROUTE_ID: &str = "0x9c081539d40ef7b02d359c5d694e006f0c1130097466cd22d062e07065c6987a";
+const TOKEN_ID: &str = "0x2d048badeeddf062caba549722fc816f3b5339fe1ea53926506b61b166ea7412";
+
+#[test]
+fn warp_route_id_preimage_is_the_padded_token_a_separator_and_the_deployer() {
+    let deployer: Address = DEPLOYER.parse().unwrap();
+    let token_address: Address = TOKEN_ADDRESS.parse().unwrap();
+    let preimage = __private::warp_route_id_preimage(token_address, deployer);
+    assert_eq!(preimage.len(), 32 + 1 + 20);
+    assert_eq!(preimage[..12], [0; 12]);
+    assert_eq!(preimage[12..32], token_address.0);
+    assert_eq!(preimage[32], 0x00);
+    assert_eq!(preimage[33..], deployer.0);
+    let hash: [u8; 32] = Sha256::digest(&preimage).into();
+    assert_eq!(HexString(hash), WARP_ROUTE_ID.parse::<HexHash>().unwrap());
+}
+
+#[test]
+fn token_id_preimage_is_the_route_the_name_and_the_decimals() {
+    let warp_route_id: HexHash = WARP_ROUTE_ID.parse().unwrap();
+    let name = get_token_name(warp_route_id);
+    assert_eq!(name, format!("Synthetic token for {WARP_ROUTE_ID}"));
+    let preimage = __private::token_id_preimage(warp_route_id, &name, 18);
+    assert_eq!(preimage.len(), 32 + name.len() + 1);
+    assert_eq!(preimage[..32], warp_route_id.0);
+    assert_eq!(preimage[32..32 + name.len()], *name.as_bytes());
+    assert_eq!(preimage.last(), Some(&18));
+    // The token ID is the hash with its last byte replaced by the decimals.
+    let mut hash: [u8; 32] = Sha256::digest(&preimage).into();
+    hash[31] = 18;
+    assert_eq!(HexString(hash), TOKEN_ID.parse::<HexHash>().unwrap());
+}